@@ -0,0 +1,209 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use sdl2::rect::Rect;
+use serde::Deserialize;
+
+use crate::QuestionState;
+
+/// How much bigger a score tile gets at the height of its pulse
+const SCORE_PULSE_GROWTH: f32 = 0.15;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TileReveal {
+    /// Tiles slide up into place from the bottom of the screen
+    Slide,
+    /// Tiles turn over to face the front
+    Flip,
+}
+
+/// Animation timings from the theme. A length of 0 turns that animation off.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnimationSettings {
+    tile_reveal: TileReveal,
+    tile_reveal_ms: u64,
+    answer_reveal_ms: u64,
+    score_pulse_ms: u64,
+    screen_fade_ms: u64,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            tile_reveal: TileReveal::Slide,
+            tile_reveal_ms: 300,
+            answer_reveal_ms: 400,
+            score_pulse_ms: 500,
+            screen_fade_ms: 400,
+        }
+    }
+}
+
+impl AnimationSettings {
+    pub fn screen_fade(&self) -> bool {
+        self.screen_fade_ms > 0
+    }
+}
+
+/// Keeps track of when things appeared on screen, to work out how far through their animations
+/// they are. It only watches the game state, so the game logic doesn't need to know about it.
+pub struct Animations {
+    settings: AnimationSettings,
+    tiles_shown: usize,
+    tile_revealed_at: [Option<Instant>; 4],
+    answer_revealed_at: Option<Instant>,
+    scores: (i32, i32),
+    score_changed_at: [Option<Instant>; 2],
+    screen_changed_at: Option<Instant>,
+}
+
+/// How far through its animation everything on screen is, from 0 to 1
+#[derive(Debug, Copy, Clone)]
+pub struct AnimationFrame {
+    tile_reveal: TileReveal,
+    tiles: [f32; 4],
+    answer: f32,
+    scores: [f32; 2],
+    /// How opaque the previous screen still is, while fading from it
+    pub screen_fade: Option<f32>,
+}
+
+impl Animations {
+    pub fn new(settings: &AnimationSettings) -> Self {
+        Animations {
+            settings: settings.clone(),
+            tiles_shown: 0,
+            tile_revealed_at: [None; 4],
+            answer_revealed_at: None,
+            scores: (0, 0),
+            score_changed_at: [None; 2],
+            screen_changed_at: None,
+        }
+    }
+
+    /// Starts the animations for anything that has appeared since the last frame
+    pub fn observe(&mut self, question_state: &QuestionState, scores: (i32, i32), now: Instant) {
+        let (tiles_shown, answer_shown) = match question_state {
+            QuestionState::Question { phase, .. } => {
+                (phase.clues_to_show(), phase.is_answer_shown())
+            }
+            _ => (0, false),
+        };
+
+        for i in 0..4 {
+            if i >= tiles_shown {
+                self.tile_revealed_at[i] = None;
+            } else if i >= self.tiles_shown {
+                self.tile_revealed_at[i] = Some(now);
+            }
+        }
+        self.tiles_shown = tiles_shown;
+
+        match (answer_shown, self.answer_revealed_at) {
+            (true, None) => self.answer_revealed_at = Some(now),
+            (false, Some(_)) => self.answer_revealed_at = None,
+            _ => {}
+        }
+
+        // only pulse when points are added, not when they're taken away again
+        if scores.0 > self.scores.0 {
+            self.score_changed_at[0] = Some(now);
+        }
+        if scores.1 > self.scores.1 {
+            self.score_changed_at[1] = Some(now);
+        }
+        self.scores = scores;
+    }
+
+    /// Starts fading from the previous screen
+    pub fn screen_changed(&mut self, now: Instant) {
+        self.screen_changed_at = Some(now);
+    }
+
+    pub fn is_animating(&self, now: Instant) -> bool {
+        let frame = self.frame(now);
+        frame.tiles.iter().any(|&p| p < 1.0)
+            || frame.answer < 1.0
+            || frame.scores.iter().any(|&p| p < 1.0)
+            || frame.screen_fade.is_some()
+    }
+
+    pub fn frame(&self, now: Instant) -> AnimationFrame {
+        let progress = |started: Option<Instant>, length_ms: u64| match started {
+            Some(started) if length_ms > 0 => {
+                let elapsed = now.saturating_duration_since(started);
+                elapsed
+                    .div_duration_f32(Duration::from_millis(length_ms))
+                    .min(1.0)
+            }
+            _ => 1.0,
+        };
+
+        let screen_fade = progress(self.screen_changed_at, self.settings.screen_fade_ms);
+
+        AnimationFrame {
+            tile_reveal: self.settings.tile_reveal,
+            tiles: [0, 1, 2, 3]
+                .map(|i| progress(self.tile_revealed_at[i], self.settings.tile_reveal_ms)),
+            answer: progress(self.answer_revealed_at, self.settings.answer_reveal_ms),
+            scores: [0, 1]
+                .map(|i| progress(self.score_changed_at[i], self.settings.score_pulse_ms)),
+            screen_fade: if screen_fade < 1.0 {
+                Some(1.0 - screen_fade)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl AnimationFrame {
+    /// Everything in its final place, for drawing still images
+    pub fn settled() -> Self {
+        AnimationFrame {
+            tile_reveal: TileReveal::Slide,
+            tiles: [1.0; 4],
+            answer: 1.0,
+            scores: [1.0; 2],
+            screen_fade: None,
+        }
+    }
+
+    /// Where a tile, or anything drawn on it, is partway through being revealed
+    pub fn tile_rect(&self, index: usize, rect: Rect, screen_height: u32) -> Rect {
+        let progress = ease_out(self.tiles[index]);
+        match self.tile_reveal {
+            TileReveal::Slide => {
+                // everything on a tile moves the same distance, so it stays on the tile
+                let offset = ((1.0 - progress) * screen_height as f32).round() as i32;
+                Rect::new(rect.x(), rect.y() + offset, rect.width(), rect.height())
+            }
+            TileReveal::Flip => scale_rect(rect, progress, 1.0),
+        }
+    }
+
+    /// The part of the answer bar revealed so far, growing out from the middle
+    pub fn answer_rect(&self, rect: Rect) -> Rect {
+        scale_rect(rect, ease_out(self.answer), 1.0)
+    }
+
+    /// A score tile, swollen if it's pulsing. Index 0 is red and 1 is blue.
+    pub fn score_rect(&self, index: usize, rect: Rect) -> Rect {
+        let scale = 1.0 + SCORE_PULSE_GROWTH * (self.scores[index] * PI).sin();
+        scale_rect(rect, scale, scale)
+    }
+}
+
+/// Starts quickly and slows into place
+fn ease_out(progress: f32) -> f32 {
+    1.0 - (1.0 - progress).powi(3)
+}
+
+/// Scales a rectangle about its centre
+fn scale_rect(rect: Rect, x_scale: f32, y_scale: f32) -> Rect {
+    let width = ((rect.width() as f32 * x_scale).round() as u32).max(1);
+    let height = ((rect.height() as f32 * y_scale).round() as u32).max(1);
+    Rect::from_center(rect.center(), width, height)
+}
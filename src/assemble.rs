@@ -0,0 +1,466 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::history::History;
+use crate::questions::{self, Question, QuestionSet, QuestionType};
+
+/// A description of the game to put together, read from YAML
+#[derive(Debug, Deserialize)]
+pub struct GameSpec {
+    /// Directory of question files and spreadsheets to pick from, searched recursively
+    pub bank: String,
+    /// Questions in each set, unless the set says otherwise
+    pub questions_per_set: usize,
+    pub sets: Vec<SetSpec>,
+    /// Questions with any of these tags are never picked
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// The most questions any one author can have in the game
+    pub max_per_author: Option<usize>,
+    /// Teams playing, which is written to the manifest so the history records them
+    #[serde(default)]
+    pub teams: Vec<String>,
+    /// Leave out questions played in the last N games
+    #[serde(default)]
+    pub exclude_recent: usize,
+    /// Leave out questions any of these teams have seen
+    #[serde(default)]
+    pub exclude_teams: Vec<String>,
+}
+
+/// What a set's questions need to be. Anything left out matches every question.
+#[derive(Debug, Deserialize)]
+pub struct SetSpec {
+    pub title: Option<String>,
+    pub count: Option<usize>,
+    #[serde(rename = "type")]
+    pub question_type: Option<QuestionType>,
+    pub difficulty: Option<String>,
+    /// The questions need at least one of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl SetSpec {
+    fn matches(&self, question: &Question) -> bool {
+        let metadata = &question.metadata;
+        self.question_type
+            .iter()
+            .all(|&question_type| question_type == question.question_type)
+            && self.difficulty.iter().all(|difficulty| {
+                metadata
+                    .difficulty
+                    .as_ref()
+                    .is_some_and(|d| d.eq_ignore_ascii_case(difficulty))
+            })
+            && (self.tags.is_empty()
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| metadata.tags.contains(&tag.to_lowercase())))
+    }
+
+    /// A title from what the set asks for, e.g. "Easy connections"
+    fn default_title(&self) -> String {
+        let kind = match self.question_type {
+            Some(QuestionType::Connection) => "connections",
+            Some(QuestionType::Sequence) => "sequences",
+            None => "questions",
+        };
+        let title = match self.difficulty {
+            Some(ref difficulty) => format!("{} {}", difficulty.to_lowercase(), kind),
+            None => kind.to_string(),
+        };
+        let mut chars = title.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+}
+
+pub fn load_game_spec<P: AsRef<Path>>(path: P) -> Result<GameSpec, Vec<String>> {
+    let file = File::open(path).map_err(|e| vec![e.to_string()])?;
+    let spec: GameSpec = serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?;
+    if spec.sets.is_empty() {
+        return Err(vec!["The game needs at least one set".into()]);
+    }
+    Ok(spec)
+}
+
+/// Picks questions from the bank for each set in the spec, in order, and writes them out as a
+/// question file per set with a manifest listing them. Given the same bank, history and random
+/// number generator it always picks the same questions.
+pub fn assemble_game<P: AsRef<Path>, R: Rng>(
+    spec: &GameSpec,
+    manifest_path: P,
+    history: &History,
+    rng: &mut R,
+    seed: u64,
+) -> Result<Vec<PathBuf>, Vec<String>> {
+    let manifest_path = manifest_path.as_ref();
+    if is_inside(manifest_path, Path::new(&spec.bank)) {
+        // the question files written next to it would be picked from the bank next time
+        return Err(vec![format!(
+            "The manifest {} can't be inside the bank {}",
+            manifest_path.display(),
+            spec.bank
+        )]);
+    }
+    let exclude_tags: Vec<String> = spec.exclude_tags.iter().map(|t| t.to_lowercase()).collect();
+
+    let mut bank: Vec<Option<Question>> = load_bank(Path::new(&spec.bank))?
+        .into_iter()
+        .filter(|q| !q.metadata.tags.iter().any(|tag| exclude_tags.contains(tag)))
+        .filter(|q| {
+            let id = q.id();
+            !history.used_in_last_games(&id, spec.exclude_recent)
+                && !history.used_by_teams(&id, &spec.exclude_teams)
+        })
+        .map(Some)
+        .collect();
+    bank.shuffle(rng);
+
+    let mut errors = vec![];
+    let mut author_counts = HashMap::new();
+    let mut question_sets = vec![];
+
+    for (i, set_spec) in spec.sets.iter().enumerate() {
+        let count = set_spec.count.unwrap_or(spec.questions_per_set);
+        let title = set_spec
+            .title
+            .clone()
+            .unwrap_or_else(|| set_spec.default_title());
+        let mut questions = vec![];
+
+        for slot in bank.iter_mut() {
+            if questions.len() == count {
+                break;
+            }
+            let question = match slot {
+                Some(q) if set_spec.matches(q) => q,
+                _ => continue,
+            };
+            if let Some(ref author) = question.metadata.author {
+                let used = author_counts.entry(author.to_lowercase()).or_insert(0);
+                if spec.max_per_author.is_some_and(|max| *used >= max) {
+                    continue;
+                }
+                *used += 1;
+            }
+            questions.push(slot.take().unwrap());
+        }
+
+        if questions.len() < count {
+            errors.push(format!(
+                "Set {} ({}) needs {} questions but only {} in the bank match",
+                i + 1,
+                title,
+                count,
+                questions.len()
+            ));
+        }
+        question_sets.push(QuestionSet { title, questions });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let stem = manifest_path
+        .file_stem()
+        .map_or_else(|| "game".into(), |s| s.to_string_lossy().into_owned());
+    let mut set_paths = vec![];
+    for (i, question_set) in question_sets.iter().enumerate() {
+        let set_path = manifest_path.with_file_name(format!("{}_set{}.txt", stem, i + 1));
+        questions::save_questions(&set_path, question_set)
+            .map_err(|e| vec![format!("Could not write {}: {}", set_path.display(), e)])?;
+        set_paths.push(set_path);
+    }
+
+    write_manifest(manifest_path, spec, &set_paths, seed).map_err(|e| {
+        vec![format!(
+            "Could not write {}: {}",
+            manifest_path.display(),
+            e
+        )]
+    })?;
+    Ok(set_paths)
+}
+
+/// Whether a file, which might not exist yet, is somewhere inside a directory
+fn is_inside(path: &Path, directory: &Path) -> bool {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (parent.canonicalize(), directory.canonicalize()) {
+        (Ok(parent), Ok(directory)) => parent.starts_with(directory),
+        _ => false,
+    }
+}
+
+fn write_manifest(
+    path: &Path,
+    spec: &GameSpec,
+    set_paths: &[PathBuf],
+    seed: u64,
+) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+
+    writeln!(file, "# Assembled from {} with seed {}", spec.bank, seed)?;
+    if !spec.teams.is_empty() {
+        writeln!(file, "teams: {}", spec.teams.join(", "))?;
+    }
+    for set_path in set_paths {
+        writeln!(file, "include_shuffle: {}", set_path.display())?;
+    }
+
+    file.flush()
+}
+
+/// Loads every question file and spreadsheet under a directory, in order of their paths. A
+/// question with no difficulty of its own takes the name of the directory it's in, below the
+/// bank, so banks sorted into easy, medium and hard directories work without metadata. A question
+/// that's in more than one file is only picked up the first time.
+fn load_bank(bank: &Path) -> Result<Vec<Question>, Vec<String>> {
+    let mut paths = vec![];
+    find_question_files(bank, &mut paths)
+        .map_err(|e| vec![format!("{}: {}", bank.display(), e)])?;
+    paths.sort();
+
+    let mut errors = vec![];
+    let mut questions = vec![];
+    let mut ids = HashSet::new();
+    for path in paths {
+        let directory_difficulty = path
+            .parent()
+            .filter(|&parent| parent != bank)
+            .and_then(|parent| parent.file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        match questions::load_questions(&path) {
+            Ok(question_set) => {
+                for mut question in question_set.questions {
+                    if !ids.insert(question.id()) {
+                        continue;
+                    }
+                    if question.metadata.difficulty.is_none() {
+                        question.metadata.difficulty = directory_difficulty.clone();
+                    }
+                    questions.push(question);
+                }
+            }
+            Err(es) => errors.extend(es.into_iter().map(|e| format!("{}: {}", path.display(), e))),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(questions)
+    } else {
+        Err(errors)
+    }
+}
+
+fn find_question_files(directory: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_question_files(&path, paths)?;
+        } else if path.extension().is_some_and(|e| {
+            ["txt", "csv", "tsv"]
+                .iter()
+                .any(|extension| e.eq_ignore_ascii_case(extension))
+        }) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::load_history;
+    use crate::questions::{Clue, Metadata};
+    use rand::rngs::StdRng;
+
+    fn question(connection: &str, author: Option<&str>, difficulty: Option<&str>) -> Question {
+        let mut question = Question::new(QuestionType::Connection, connection.into());
+        question.clues = (1..=4)
+            .map(|i| Clue::TextClue(format!("{} {}", connection, i)))
+            .collect();
+        question.metadata = Metadata {
+            author: author.map(String::from),
+            difficulty: difficulty.map(String::from),
+            tags: vec![],
+        };
+        question
+    }
+
+    fn tagged(connection: &str, tags: &[&str]) -> Question {
+        let mut question = question(connection, None, None);
+        question.metadata.tags = tags.iter().map(|&tag| tag.into()).collect();
+        question
+    }
+
+    fn set_spec(count: usize, difficulty: Option<&str>) -> SetSpec {
+        SetSpec {
+            title: None,
+            count: Some(count),
+            question_type: None,
+            difficulty: difficulty.map(String::from),
+            tags: vec![],
+        }
+    }
+
+    /// A bank in a fresh temporary directory, with the questions saved in files at the paths
+    fn bank(name: &str, files: Vec<(&str, Vec<Question>)>) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("assemble-test-{}", name));
+        let _ = fs::remove_dir_all(&root);
+        for (path, questions) in files {
+            let path = root.join("bank").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let question_set = QuestionSet {
+                title: "Bank".into(),
+                questions,
+            };
+            questions::save_questions(&path, &question_set).unwrap();
+        }
+        root
+    }
+
+    fn game_spec(root: &Path, sets: Vec<SetSpec>) -> GameSpec {
+        GameSpec {
+            bank: root.join("bank").to_string_lossy().into_owned(),
+            questions_per_set: 4,
+            sets,
+            exclude_tags: vec![],
+            max_per_author: None,
+            teams: vec![],
+            exclude_recent: 0,
+            exclude_teams: vec![],
+        }
+    }
+
+    /// Assembles a game next to the bank and reads back the connections picked for each set, in
+    /// alphabetical order
+    fn assemble(root: &Path, spec: &GameSpec) -> Result<Vec<Vec<String>>, Vec<String>> {
+        let history = load_history(root.join("history.yaml")).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let set_paths = assemble_game(spec, root.join("game.txt"), &history, &mut rng, 1)?;
+        Ok(set_paths
+            .iter()
+            .map(|path| {
+                let mut connections: Vec<String> = questions::load_questions(path)
+                    .unwrap()
+                    .questions
+                    .into_iter()
+                    .map(|q| q.connection)
+                    .collect();
+                connections.sort();
+                connections
+            })
+            .collect())
+    }
+
+    #[test]
+    fn sets_match_on_type_difficulty_and_any_of_their_tags() {
+        let mut spec = set_spec(1, Some("Hard"));
+        spec.tags = vec!["Music".into(), "film".into()];
+        let mut q = tagged("Bands", &["music", "history"]);
+        q.metadata.difficulty = Some("hard".into());
+        assert!(spec.matches(&q));
+
+        spec.question_type = Some(QuestionType::Sequence);
+        assert!(!spec.matches(&q));
+        spec.question_type = None;
+
+        q.metadata.difficulty = None;
+        assert!(!spec.matches(&q));
+        q.metadata.difficulty = Some("HARD".into());
+        q.metadata.tags = vec!["history".into()];
+        assert!(!spec.matches(&q));
+    }
+
+    #[test]
+    fn no_author_has_more_than_the_maximum() {
+        let root = bank(
+            "authors",
+            vec![(
+                "questions.txt",
+                vec![
+                    question("A1", Some("Ann"), None),
+                    question("A2", Some("ann"), None),
+                    question("A3", Some("Ann"), None),
+                    question("B1", Some("Bob"), None),
+                    question("B2", Some("Bob"), None),
+                    question("N1", None, None),
+                ],
+            )],
+        );
+        let mut spec = game_spec(&root, vec![set_spec(2, None), set_spec(3, None)]);
+        spec.max_per_author = Some(2);
+
+        let sets = assemble(&root, &spec).unwrap();
+        let picked: Vec<&String> = sets.iter().flatten().collect();
+        assert_eq!(picked.len(), 5);
+        assert_eq!(picked.iter().filter(|c| c.starts_with('A')).count(), 2);
+
+        spec.sets = vec![set_spec(6, None)];
+        assert!(assemble(&root, &spec).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn questions_with_excluded_tags_are_never_picked() {
+        let root = bank(
+            "tags",
+            vec![(
+                "questions.txt",
+                vec![
+                    tagged("Clean", &["music"]),
+                    tagged("Rude", &["music", "rude"]),
+                    tagged("Untagged", &[]),
+                ],
+            )],
+        );
+        let mut spec = game_spec(&root, vec![set_spec(2, None)]);
+        spec.exclude_tags = vec!["Rude".into()];
+        assert_eq!(
+            assemble(&root, &spec).unwrap(),
+            vec![vec!["Clean", "Untagged"]]
+        );
+
+        spec.sets = vec![set_spec(3, None)];
+        let errors = assemble(&root, &spec).unwrap_err();
+        assert!(errors[0].contains("needs 3 questions but only 2"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn questions_without_a_difficulty_take_their_directory_name() {
+        let root = bank(
+            "difficulty",
+            vec![
+                ("easy/a.txt", vec![question("Apples", None, None)]),
+                ("hard/b.txt", vec![question("Bears", None, None)]),
+                ("hard/c.txt", vec![question("Cats", None, Some("Easy"))]),
+                ("d.txt", vec![question("Dogs", None, None)]),
+            ],
+        );
+        let spec = game_spec(
+            &root,
+            vec![set_spec(2, Some("Easy")), set_spec(1, Some("hard"))],
+        );
+        assert_eq!(
+            assemble(&root, &spec).unwrap(),
+            vec![vec!["Apples", "Cats"], vec!["Bears"]]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
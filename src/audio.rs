@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use sdl2::mixer::{
+    AudioFormat, Channel, Chunk, InitFlag, Sdl2MixerContext, AUDIO_F32LSB, AUDIO_F32MSB,
+    AUDIO_S16LSB, AUDIO_S16MSB, AUDIO_S32LSB, AUDIO_S32MSB, AUDIO_S8, AUDIO_U16LSB, AUDIO_U16MSB,
+    AUDIO_U8, DEFAULT_CHANNELS, DEFAULT_FORMAT, DEFAULT_FREQUENCY, MAX_VOLUME,
+};
+use sdl2::{AudioSubsystem, Sdl};
+use serde::Deserialize;
+
+/// Loudness of the built-in beeps, as a fraction of full scale
+const TONE_AMPLITUDE: f32 = 0.18;
+
+/// Something happening in the game that can have a sound
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cue {
+    Buzzer,
+    CountInTick,
+    ClueRevealed,
+    ClockStopped,
+    /// The last few seconds of the clock, when the countdown appears
+    FinalSeconds,
+    TimeUp,
+    /// The team in play got it wrong and the question goes to the other team
+    PassedOver,
+    Correct,
+    Incorrect,
+}
+
+impl Cue {
+    pub const ALL: [Cue; 9] = [
+        Cue::Buzzer,
+        Cue::CountInTick,
+        Cue::ClueRevealed,
+        Cue::ClockStopped,
+        Cue::FinalSeconds,
+        Cue::TimeUp,
+        Cue::PassedOver,
+        Cue::Correct,
+        Cue::Incorrect,
+    ];
+
+    /// Frequency and length in milliseconds of the beep used when there's no sound file
+    fn default_tone(self) -> (i32, i32) {
+        match self {
+            Cue::Buzzer => (220, 400),
+            Cue::CountInTick => (880, 50),
+            Cue::ClueRevealed => (660, 120),
+            Cue::ClockStopped => (440, 250),
+            Cue::FinalSeconds => (1320, 150),
+            Cue::TimeUp => (165, 800),
+            Cue::PassedOver => (330, 300),
+            Cue::Correct => (990, 300),
+            Cue::Incorrect => (110, 500),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SoundConfig {
+    /// Master volume, from 0 to 1
+    volume: f32,
+    muted: bool,
+    /// A sound file for each cue, or None to leave the cue silent. Cues that aren't mentioned
+    /// get a built-in beep.
+    cues: HashMap<Cue, Option<String>>,
+}
+
+impl Default for SoundConfig {
+    fn default() -> Self {
+        SoundConfig {
+            volume: 1.0,
+            muted: false,
+            cues: HashMap::new(),
+        }
+    }
+}
+
+/// Loads a sound config file, using the defaults if it doesn't exist
+pub fn load_sound_config<P: AsRef<Path>>(path: P) -> Result<SoundConfig, Vec<String>> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SoundConfig::default()),
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+
+    let config: SoundConfig = serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?;
+
+    if (0.0..=1.0).contains(&config.volume) {
+        Ok(config)
+    } else {
+        Err(vec![format!(
+            "Volume must be between 0 and 1, not {}",
+            config.volume
+        )])
+    }
+}
+
+pub struct Sounds {
+    _audio_subsystem: AudioSubsystem,
+    // only needed for compressed formats, so it's fine if it isn't available
+    _mixer_context: Option<Sdl2MixerContext>,
+    chunks: HashMap<Cue, Chunk>,
+    muted: bool,
+}
+
+impl Sounds {
+    pub fn new(sdl_context: &Sdl, config: &SoundConfig) -> Result<Self, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        sdl2::mixer::open_audio(DEFAULT_FREQUENCY, DEFAULT_FORMAT, DEFAULT_CHANNELS, 1024)?;
+        let mixer_context = sdl2::mixer::init(InitFlag::OGG).ok();
+        // the device may not have given us the spec we asked for
+        let spec = sdl2::mixer::query_spec()?;
+
+        let volume = (config.volume * MAX_VOLUME as f32).round() as i32;
+        let mut chunks = HashMap::new();
+        for &cue in Cue::ALL.iter() {
+            let chunk = match config.cues.get(&cue) {
+                Some(None) => continue,
+                Some(Some(path)) => match Chunk::from_file(path) {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        eprintln!("Could not load sound {}, using a beep instead: {}", path, e);
+                        let (frequency, length_ms) = cue.default_tone();
+                        square_wave(spec, frequency, length_ms, TONE_AMPLITUDE)?
+                    }
+                },
+                None => {
+                    let (frequency, length_ms) = cue.default_tone();
+                    square_wave(spec, frequency, length_ms, TONE_AMPLITUDE)?
+                }
+            };
+            chunks.insert(cue, chunk);
+        }
+        for chunk in chunks.values_mut() {
+            chunk.set_volume(volume);
+        }
+
+        Ok(Sounds {
+            _audio_subsystem: audio_subsystem,
+            _mixer_context: mixer_context,
+            chunks,
+            muted: config.muted,
+        })
+    }
+
+    pub fn play(&self, cue: Cue) {
+        if self.muted {
+            return;
+        }
+        if let Some(chunk) = self.chunks.get(&cue) {
+            if let Err(e) = Channel::all().play(chunk, 0) {
+                eprintln!("Could not play sound: {}", e);
+            }
+        }
+    }
+
+    /// Returns whether sound is now muted
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        if self.muted {
+            Channel::all().halt();
+        }
+        self.muted
+    }
+}
+
+/// Synthesises a square wave in the mixer's spec, as returned by `query_spec`
+fn square_wave(
+    (device_frequency, format, channels): (i32, AudioFormat, i32),
+    frequency: i32,
+    length_ms: i32,
+    amplitude: f32,
+) -> Result<Chunk, String> {
+    let frames = device_frequency * length_ms / 1000;
+    let half_period = (device_frequency / frequency / 2).max(1);
+    let high = encode_sample(amplitude, format)?;
+    let low = encode_sample(-amplitude, format)?;
+
+    let mut bytes = Vec::with_capacity(frames as usize * channels as usize * high.len());
+    for i in 0..frames {
+        let sample = if (i / half_period) % 2 == 0 {
+            &high
+        } else {
+            &low
+        };
+        for _ in 0..channels {
+            bytes.extend_from_slice(sample);
+        }
+    }
+
+    Chunk::from_raw_buffer(bytes.into_boxed_slice())
+}
+
+/// Encodes a sample between -1 and 1 in an SDL audio format
+fn encode_sample(level: f32, format: AudioFormat) -> Result<Vec<u8>, String> {
+    let level = level.max(-1.0).min(1.0);
+    Ok(match format {
+        AUDIO_U8 => vec![(128.0 + level * 127.0) as u8],
+        AUDIO_S8 => vec![(level * 127.0) as i8 as u8],
+        AUDIO_U16LSB => ((32768.0 + level * 32767.0) as u16).to_le_bytes().to_vec(),
+        AUDIO_U16MSB => ((32768.0 + level * 32767.0) as u16).to_be_bytes().to_vec(),
+        AUDIO_S16LSB => ((level * 32767.0) as i16).to_le_bytes().to_vec(),
+        AUDIO_S16MSB => ((level * 32767.0) as i16).to_be_bytes().to_vec(),
+        AUDIO_S32LSB => ((level as f64 * i32::MAX as f64) as i32)
+            .to_le_bytes()
+            .to_vec(),
+        AUDIO_S32MSB => ((level as f64 * i32::MAX as f64) as i32)
+            .to_be_bytes()
+            .to_vec(),
+        AUDIO_F32LSB => level.to_le_bytes().to_vec(),
+        AUDIO_F32MSB => level.to_be_bytes().to_vec(),
+        _ => return Err(format!("Unsupported audio format {:#06x}", format)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_encoded_in_the_device_format() {
+        assert_eq!(encode_sample(0.0, AUDIO_U8), Ok(vec![128]));
+        assert_eq!(encode_sample(-1.0, AUDIO_S8), Ok(vec![0x81]));
+        assert_eq!(encode_sample(1.0, AUDIO_S16LSB), Ok(vec![0xff, 0x7f]));
+        assert_eq!(encode_sample(1.0, AUDIO_S16MSB), Ok(vec![0x7f, 0xff]));
+        assert_eq!(encode_sample(0.0, AUDIO_U16LSB), Ok(vec![0x00, 0x80]));
+        assert_eq!(
+            encode_sample(0.5, AUDIO_F32LSB),
+            Ok(0.5f32.to_le_bytes().to_vec())
+        );
+        assert_eq!(
+            encode_sample(2.0, AUDIO_S32MSB),
+            Ok(i32::MAX.to_be_bytes().to_vec())
+        );
+        assert!(encode_sample(0.0, 0x1234).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+/// How long after the first buzz wins that a buzz which happened earlier, but arrived later (e.g.
+/// from a phone over wifi), can still take its place
+const LATE_ARRIVAL_WINDOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Copy, Clone)]
+pub struct Buzz {
+    pub team: Team,
+    at: Instant,
+    /// Time between the buzzers being armed and the team buzzing
+    pub elapsed: Duration,
+    /// The team whose buzz this one took the place of, if it arrived late
+    pub overturned: Option<Team>,
+}
+
+/// What became of a buzz that wasn't locked out
+#[derive(Debug, Copy, Clone)]
+pub enum BuzzResult {
+    /// The first buzz since the buzzers were reset
+    Won(Buzz),
+    /// A buzz that happened before the winning one but arrived after it, which takes its place
+    Overturned(Buzz),
+}
+
+/// First-in buzzer lockout. Once a team has buzzed, every other buzz is ignored until the
+/// buzzers are reset.
+#[derive(Debug)]
+pub struct Buzzers {
+    armed_at: Instant,
+    winner: Option<Buzz>,
+    /// When the first buzz since the buzzers were reset arrived. Overturning it doesn't restart
+    /// the window for late buzzes, so the buzzers can't keep changing hands.
+    decided_at: Instant,
+}
+
+impl Buzzers {
+    pub fn new() -> Self {
+        Buzzers {
+            armed_at: Instant::now(),
+            winner: None,
+            decided_at: Instant::now(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.armed_at = Instant::now();
+        self.winner = None;
+    }
+
+    /// Returns whether the buzz won or overturned the winner, or None if the buzzers were
+    /// already locked out or it happened before they were reset
+    pub fn buzz(&mut self, team: Team, at: Instant) -> Option<BuzzResult> {
+        if at < self.armed_at {
+            return None;
+        }
+        if let Some(winner) = self.winner {
+            let arrived_late = self.decided_at.elapsed() <= LATE_ARRIVAL_WINDOW && at < winner.at;
+            if !arrived_late || winner.team == team {
+                return None;
+            }
+        }
+
+        let buzz = Buzz {
+            team,
+            at,
+            elapsed: at - self.armed_at,
+            overturned: self.winner.map(|winner| winner.team),
+        };
+        self.winner = Some(buzz);
+        Some(match buzz.overturned {
+            Some(_) => BuzzResult::Overturned(buzz),
+            None => {
+                self.decided_at = Instant::now();
+                BuzzResult::Won(buzz)
+            }
+        })
+    }
+
+    pub fn winner(&self) -> Option<Buzz> {
+        self.winner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn first_buzz_wins_and_locks_out_the_rest() {
+        let mut buzzers = Buzzers::new();
+        let armed_at = buzzers.armed_at;
+
+        let won = buzzers.buzz(Team::Red, armed_at + ms(100));
+        assert!(matches!(won, Some(BuzzResult::Won(buzz)) if buzz.elapsed == ms(100)));
+        assert!(buzzers.buzz(Team::Blue, armed_at + ms(200)).is_none());
+        assert!(buzzers.buzz(Team::Red, armed_at + ms(50)).is_none());
+        assert_eq!(buzzers.winner().unwrap().team, Team::Red);
+
+        buzzers.reset();
+        let won = buzzers.buzz(Team::Blue, buzzers.armed_at + ms(10));
+        assert!(matches!(won, Some(BuzzResult::Won(buzz)) if buzz.team == Team::Blue));
+    }
+
+    #[test]
+    fn earlier_buzz_arriving_late_overturns_the_winner() {
+        let mut buzzers = Buzzers::new();
+        let armed_at = buzzers.armed_at;
+
+        buzzers.buzz(Team::Red, armed_at + ms(300));
+        let overturned = buzzers.buzz(Team::Blue, armed_at + ms(200));
+        assert!(matches!(
+            overturned,
+            Some(BuzzResult::Overturned(buzz))
+                if buzz.team == Team::Blue && buzz.overturned == Some(Team::Red)
+        ));
+        assert_eq!(buzzers.winner().unwrap().team, Team::Blue);
+    }
+
+    #[test]
+    fn late_buzzes_only_count_within_the_window_from_the_first_decision() {
+        let mut buzzers = Buzzers::new();
+        let armed_at = buzzers.armed_at;
+
+        buzzers.buzz(Team::Red, armed_at + ms(300));
+        let decided_at = buzzers.decided_at;
+        buzzers.buzz(Team::Blue, armed_at + ms(200));
+        assert_eq!(buzzers.decided_at, decided_at);
+
+        buzzers.decided_at = Instant::now() - LATE_ARRIVAL_WINDOW - ms(1);
+        assert!(buzzers.buzz(Team::Red, armed_at + ms(100)).is_none());
+        assert_eq!(buzzers.winner().unwrap().team, Team::Blue);
+    }
+
+    #[test]
+    fn buzzes_from_before_the_reset_are_ignored() {
+        let mut buzzers = Buzzers::new();
+        buzzers.reset();
+        let before = buzzers.armed_at - ms(1);
+        assert!(buzzers.buzz(Team::Red, before).is_none());
+        assert!(buzzers.winner().is_none());
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hash;
+use std::path::Path;
+
+use sdl2::controller::Button;
+use sdl2::keyboard::Keycode;
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+
+use crate::buzzers::Team;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Next,
+    Stop,
+    Correct,
+    Incorrect,
+    Undo,
+    Pause,
+    RedScoreUp,
+    RedScoreDown,
+    BlueScoreUp,
+    BlueScoreDown,
+    BuzzRed,
+    BuzzBlue,
+    ResetBuzzers,
+    ToggleHelp,
+    ToggleCaptions,
+    ToggleMute,
+    ToggleFullscreen,
+    Quit,
+}
+
+impl Action {
+    /// Every action, in the order they are listed in the help overlay
+    pub const ALL: [Action; 18] = [
+        Action::Next,
+        Action::Stop,
+        Action::Correct,
+        Action::Incorrect,
+        Action::Undo,
+        Action::Pause,
+        Action::RedScoreUp,
+        Action::RedScoreDown,
+        Action::BlueScoreUp,
+        Action::BlueScoreDown,
+        Action::BuzzRed,
+        Action::BuzzBlue,
+        Action::ResetBuzzers,
+        Action::ToggleHelp,
+        Action::ToggleCaptions,
+        Action::ToggleMute,
+        Action::ToggleFullscreen,
+        Action::Quit,
+    ];
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Next => "Next clue / next screen",
+            Action::Stop => "Stop the clock",
+            Action::Correct => "Correct answer",
+            Action::Incorrect => "Incorrect answer",
+            Action::Undo => "Undo last action",
+            Action::Pause => "Pause / resume the clock",
+            Action::RedScoreUp => "Red score +1",
+            Action::RedScoreDown => "Red score -1",
+            Action::BlueScoreUp => "Blue score +1",
+            Action::BlueScoreDown => "Blue score -1",
+            Action::BuzzRed => "Red buzzer",
+            Action::BuzzBlue => "Blue buzzer",
+            Action::ResetBuzzers => "Reset buzzers",
+            Action::ToggleHelp => "Show / hide this help",
+            Action::ToggleCaptions => "Show / hide picture captions",
+            Action::ToggleMute => "Mute / unmute sounds",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::Quit => "Quit",
+        }
+    }
+
+    fn default_keys(self) -> &'static [Keycode] {
+        match self {
+            Action::Next => &[Keycode::N],
+            Action::Stop => &[Keycode::S],
+            Action::Correct => &[Keycode::C],
+            Action::Incorrect => &[Keycode::I],
+            Action::Undo => &[Keycode::U],
+            Action::Pause => &[Keycode::P],
+            Action::RedScoreUp => &[Keycode::Num1],
+            Action::RedScoreDown => &[Keycode::Q],
+            Action::BlueScoreUp => &[Keycode::Num0],
+            Action::BlueScoreDown => &[Keycode::O],
+            Action::BuzzRed => &[Keycode::LShift],
+            Action::BuzzBlue => &[Keycode::RShift],
+            Action::ResetBuzzers => &[Keycode::R],
+            Action::ToggleHelp => &[Keycode::H, Keycode::F1],
+            Action::ToggleCaptions => &[Keycode::T],
+            Action::ToggleMute => &[Keycode::M],
+            Action::ToggleFullscreen => &[Keycode::F11],
+            Action::Quit => &[Keycode::Escape],
+        }
+    }
+
+    fn default_controller_buttons(self) -> &'static [Button] {
+        match self {
+            Action::Next => &[Button::A, Button::DPadRight],
+            Action::Stop => &[Button::B],
+            Action::Correct => &[Button::X],
+            Action::Incorrect => &[Button::Y],
+            Action::Undo => &[Button::Back],
+            Action::Pause => &[Button::Start],
+            Action::ToggleHelp => &[Button::Guide],
+            Action::RedScoreUp
+            | Action::RedScoreDown
+            | Action::BlueScoreUp
+            | Action::BlueScoreDown
+            | Action::BuzzRed
+            | Action::BuzzBlue
+            | Action::ResetBuzzers
+            | Action::ToggleCaptions
+            | Action::ToggleMute
+            | Action::ToggleFullscreen
+            | Action::Quit => &[],
+        }
+    }
+
+    fn default_joystick_buttons(self) -> &'static [u8] {
+        match self {
+            Action::Next => &[0],
+            Action::Stop => &[1],
+            Action::Correct => &[2],
+            Action::Incorrect => &[3],
+            _ => &[],
+        }
+    }
+}
+
+/// An action along with the keys, controller buttons and joystick buttons bound to it
+type Binding = (Action, Vec<Keycode>, Vec<Button>, Vec<u8>);
+
+/// Bindings from keys, game controller buttons and raw joystick buttons to actions
+#[derive(Debug)]
+pub struct Controls {
+    key_actions: HashMap<Keycode, Action>,
+    controller_actions: HashMap<Button, Action>,
+    joystick_actions: HashMap<u8, Action>,
+    buzzer_controllers: HashMap<Team, u32>,
+    bindings: Vec<(Action, Vec<Keycode>, Vec<Button>)>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self::from_bindings(
+            HashMap::new(),
+            Action::ALL
+                .iter()
+                .map(|&action| {
+                    (
+                        action,
+                        action.default_keys().to_vec(),
+                        action.default_controller_buttons().to_vec(),
+                        action.default_joystick_buttons().to_vec(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Controls {
+    fn from_bindings(buzzer_controllers: HashMap<Team, u32>, bindings: Vec<Binding>) -> Self {
+        let mut key_actions = HashMap::new();
+        let mut controller_actions = HashMap::new();
+        let mut joystick_actions = HashMap::new();
+
+        for (action, keycodes, buttons, joystick_buttons) in &bindings {
+            for keycode in keycodes {
+                key_actions.insert(*keycode, *action);
+            }
+            for button in buttons {
+                controller_actions.insert(*button, *action);
+            }
+            for joystick_button in joystick_buttons {
+                joystick_actions.insert(*joystick_button, *action);
+            }
+        }
+
+        Controls {
+            key_actions,
+            controller_actions,
+            joystick_actions,
+            buzzer_controllers,
+            bindings: bindings
+                .into_iter()
+                .map(|(action, keycodes, buttons, _)| (action, keycodes, buttons))
+                .collect(),
+        }
+    }
+
+    pub fn action_for_key(&self, keycode: Keycode) -> Option<Action> {
+        self.key_actions.get(&keycode).copied()
+    }
+
+    pub fn action_for_controller_button(&self, button: Button) -> Option<Action> {
+        self.controller_actions.get(&button).copied()
+    }
+
+    pub fn action_for_joystick_button(&self, button_idx: u8) -> Option<Action> {
+        self.joystick_actions.get(&button_idx).copied()
+    }
+
+    /// Names of the keys bound to an action
+    pub fn key_names(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(a, _, _)| *a == action)
+            .flat_map(|(_, keycodes, _)| keycodes.iter().map(|k| k.name()))
+            .collect()
+    }
+
+    /// The team whose buzzer is the controller or joystick in the given slot, if any
+    pub fn buzzer_team_for_slot(&self, slot: u32) -> Option<Team> {
+        self.buzzer_controllers
+            .iter()
+            .find(|(_, &s)| s == slot)
+            .map(|(&team, _)| team)
+    }
+
+    /// One line per action, e.g. "N, PageDown (pad: a): Next clue / next screen"
+    pub fn help_lines(&self) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, keycodes, buttons)| !keycodes.is_empty() || !buttons.is_empty())
+            .map(|(action, keycodes, buttons)| {
+                let key_names: Vec<String> = keycodes.iter().map(|k| k.name()).collect();
+                let button_names: Vec<String> = buttons.iter().map(|b| b.string()).collect();
+                if button_names.is_empty() {
+                    format!("{}: {}", key_names.join(", "), action.description())
+                } else {
+                    format!(
+                        "{} (pad: {}): {}",
+                        key_names.join(", "),
+                        button_names.join(", "),
+                        action.description()
+                    )
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ControlsFile {
+    keys: HashMap<Action, Vec<String>>,
+    controller_buttons: HashMap<Action, Vec<String>>,
+    joystick_buttons: HashMap<Action, Vec<u8>>,
+    buzzer_controllers: HashMap<Team, u32>,
+}
+
+/// Loads a controls file. Actions not mentioned in a section keep their default bindings for
+/// that section; if the file doesn't exist the defaults are used for everything.
+pub fn load_controls<P: AsRef<Path>>(path: P) -> Result<Controls, Vec<String>> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Controls::default()),
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+
+    let value: serde_yaml::Value =
+        serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?;
+    // before controllers were added, actions were listed at the top with their keys, which
+    // would otherwise be quietly ignored
+    let old_layout_actions: Vec<&str> = value
+        .as_mapping()
+        .into_iter()
+        .flat_map(|mapping| mapping.iter())
+        .filter_map(|(name, _)| name.as_str())
+        .filter(|&name| {
+            let deserializer: StrDeserializer<ValueError> = name.into_deserializer();
+            Action::deserialize(deserializer).is_ok()
+        })
+        .collect();
+    if !old_layout_actions.is_empty() {
+        return Err(vec![format!(
+            "Keys for {} need to go under keys:, as this is the old layout of the file",
+            old_layout_actions.join(", ")
+        )]);
+    }
+    let controls_file: ControlsFile =
+        serde_yaml::from_value(value).map_err(|e| vec![e.to_string()])?;
+
+    let mut errors = vec![];
+    let mut bindings = vec![];
+
+    for &action in Action::ALL.iter() {
+        let keycodes = match controls_file.keys.get(&action) {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| match Keycode::from_name(name) {
+                    Some(k) => Some(k),
+                    None => {
+                        errors.push(format!("Unknown key {} for {:?}", name, action));
+                        None
+                    }
+                })
+                .collect(),
+            None => action.default_keys().to_vec(),
+        };
+        let buttons = match controls_file.controller_buttons.get(&action) {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| match Button::from_string(name) {
+                    Some(b) => Some(b),
+                    None => {
+                        errors.push(format!(
+                            "Unknown controller button {} for {:?}",
+                            name, action
+                        ));
+                        None
+                    }
+                })
+                .collect(),
+            None => action.default_controller_buttons().to_vec(),
+        };
+        let joystick_buttons = match controls_file.joystick_buttons.get(&action) {
+            Some(button_idxs) => button_idxs.clone(),
+            None => action.default_joystick_buttons().to_vec(),
+        };
+        bindings.push((action, keycodes, buttons, joystick_buttons));
+    }
+
+    report_conflicts(
+        "Key",
+        bindings
+            .iter()
+            .flat_map(|(action, keycodes, _, _)| keycodes.iter().map(move |&k| (*action, k))),
+        |k| k.name(),
+        &mut errors,
+    );
+    report_conflicts(
+        "Controller button",
+        bindings
+            .iter()
+            .flat_map(|(action, _, buttons, _)| buttons.iter().map(move |&b| (*action, b))),
+        |b| b.string(),
+        &mut errors,
+    );
+    report_conflicts(
+        "Joystick button",
+        bindings
+            .iter()
+            .flat_map(|(action, _, _, joystick_buttons)| {
+                joystick_buttons.iter().map(move |&b| (*action, b))
+            }),
+        |b| b.to_string(),
+        &mut errors,
+    );
+
+    if errors.is_empty() {
+        Ok(Controls::from_bindings(
+            controls_file.buzzer_controllers,
+            bindings,
+        ))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reports anything bound to more than one action, including by default, as only one of them
+/// could ever happen
+fn report_conflicts<T: Copy + Eq + Hash>(
+    kind: &str,
+    bindings: impl Iterator<Item = (Action, T)>,
+    name: impl Fn(T) -> String,
+    errors: &mut Vec<String>,
+) {
+    let mut actions = HashMap::new();
+    for (action, input) in bindings {
+        match actions.insert(input, action) {
+            Some(other) if other != action => errors.push(format!(
+                "{} {} is bound to both {:?} and {:?}",
+                kind,
+                name(input),
+                other,
+                action
+            )),
+            _ => {}
+        }
+    }
+}
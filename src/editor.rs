@@ -0,0 +1,860 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::video::{Window, WindowContext};
+use sdl2::{Sdl, VideoSubsystem};
+
+use crate::animation::AnimationFrame;
+use crate::fonts::FontChain;
+use crate::preview;
+use crate::questions::{self, parse_tags, Clue, Picture, Question, QuestionSet, QuestionType};
+use crate::texture_cache::TextureCache;
+use crate::theme::{ElementFonts, Theme};
+use crate::{Metrics, QuestionState, FONT_RELOAD_DELAY};
+
+const EDITOR_WINDOW_SIZE: (u32, u32) = (1280, 720);
+const BACKGROUND_COLOUR: Color = Color::RGB(0x22, 0x22, 0x22);
+const FIELD_COLOUR: Color = Color::RGB(0x33, 0x33, 0x44);
+const LABEL_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
+const TEXT_COLOUR: Color = Color::RGB(0xff, 0xff, 0xff);
+const STATUS_COLOUR: Color = Color::RGB(0xff, 0xcc, 0x66);
+const HINT_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
+
+const HINTS: [&str; 6] = [
+    "Tab/Up/Down: move between fields",
+    "Page Up/Page Down: previous/next question",
+    "Ctrl+N: new question, Ctrl+D: delete question",
+    "F2: switch between connection and sequence",
+    "Drop an image on the window to use it for the selected clue",
+    "Ctrl+S: save, Escape: quit",
+];
+
+/// Something in a question that can be edited
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Field {
+    Title,
+    Connection,
+    Clue(usize),
+    /// The image file for a clue, which makes it a picture clue with the clue as its caption
+    Picture(usize),
+    Note(usize),
+    Author,
+    Difficulty,
+    Tags,
+}
+
+/// The question set being edited, and which part of it has the cursor
+struct Editor {
+    path: PathBuf,
+    question_set: QuestionSet,
+    question: usize,
+    field: Field,
+    /// The text of the selected field as it's being typed, which can differ from the question
+    /// until it's valid, as with tags and picture paths
+    buffer: String,
+    /// Byte offset of the cursor in the buffer
+    cursor: usize,
+    status: String,
+    unsaved: bool,
+    /// Whether quitting with unsaved changes has been asked for once already
+    quit_warned: bool,
+    /// Whether the question has changed since the preview was drawn
+    preview_stale: bool,
+}
+
+/// Opens a window for writing and editing the questions in a question file, with a preview of
+/// each question's answer screen as it'll look in the game. The file is created when it's first
+/// saved if it doesn't exist.
+pub fn run_editor(
+    sdl_context: &Sdl,
+    video_subsystem: &VideoSubsystem,
+    ttf_context: &Sdl2TtfContext,
+    font_paths: &[String],
+    theme: &Theme,
+    path: &str,
+) -> Result<(), String> {
+    let mut editor = Editor::load(path).map_err(|es| es.join("\n"))?;
+
+    let window = video_subsystem
+        .window(
+            &format!("Only Connect Trivia - Editing {}", path),
+            EDITOR_WINDOW_SIZE.0,
+            EDITOR_WINDOW_SIZE.1,
+        )
+        .position_centered()
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut canvas = window
+        .into_canvas()
+        .present_vsync()
+        .accelerated()
+        .build()
+        .map_err(|e| e.to_string())?;
+    let texture_creator = canvas.texture_creator();
+    let mut textures = TextureCache::new(&texture_creator);
+
+    let mut layout = Layout::new(canvas.output_size()?);
+    let mut form_font = FontChain::load(ttf_context, font_paths, layout.font_size())?;
+    let mut preview_metrics = Metrics::from_window_dimensions(layout.preview.size());
+    let mut preview_fonts = theme.load_fonts(ttf_context, font_paths, preview_metrics.text_size)?;
+    let mut preview_texture = None;
+
+    video_subsystem.text_input().start();
+    let clipboard = video_subsystem.clipboard();
+    let mut event_pump = sdl_context.event_pump()?;
+    // when the window was last resized, while the fonts are still the size they were before
+    let mut resized_at: Option<Instant> = None;
+
+    'running: loop {
+        let first_event = match resized_at {
+            Some(resized_at) => {
+                let settles_in =
+                    (resized_at + FONT_RELOAD_DELAY).saturating_duration_since(Instant::now());
+                event_pump.wait_event_timeout(settles_in.as_millis() as u32 + 1)
+            }
+            None => Some(event_pump.wait_event()),
+        };
+        for event in first_event.into_iter().chain(event_pump.poll_iter()) {
+            match event {
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: WindowEvent::Close,
+                    ..
+                } => {
+                    if editor.request_quit() {
+                        break 'running;
+                    }
+                }
+                Event::TextInput { text, .. } => editor.insert(&text),
+                Event::DropFile { filename, .. } => editor.drop_picture(&filename),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    ..
+                } => {
+                    let ctrl = keymod
+                        .intersects(Mod::LCTRLMOD | Mod::RCTRLMOD | Mod::LGUIMOD | Mod::RGUIMOD);
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    match keycode {
+                        Keycode::Escape => {
+                            if editor.request_quit() {
+                                break 'running;
+                            }
+                        }
+                        Keycode::Tab if shift => editor.move_field(-1),
+                        Keycode::Tab | Keycode::Down | Keycode::Return => editor.move_field(1),
+                        Keycode::Up => editor.move_field(-1),
+                        Keycode::PageUp => editor.move_question(-1),
+                        Keycode::PageDown => editor.move_question(1),
+                        Keycode::Left => editor.move_cursor(-1),
+                        Keycode::Right => editor.move_cursor(1),
+                        Keycode::Home => editor.cursor = 0,
+                        Keycode::End => editor.cursor = editor.buffer.len(),
+                        Keycode::Backspace => editor.delete(-1),
+                        Keycode::Delete => editor.delete(1),
+                        Keycode::F2 => editor.switch_type(),
+                        Keycode::N if ctrl => editor.new_question(),
+                        Keycode::D if ctrl => editor.delete_question(),
+                        Keycode::S if ctrl => editor.save(),
+                        Keycode::V if ctrl => match clipboard.clipboard_text() {
+                            // fields are a single line
+                            Ok(text) => editor.insert(&text.replace(['\r', '\n'], " ")),
+                            Err(e) => editor.status = format!("Could not paste: {}", e),
+                        },
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let output_size = canvas.output_size()?;
+        if output_size != layout.size {
+            // the old preview is stretched to fit until the size settles
+            layout = Layout::new(output_size);
+            resized_at = Some(Instant::now());
+        }
+        if resized_at.is_some_and(|at| at.elapsed() >= FONT_RELOAD_DELAY) {
+            form_font = FontChain::load(ttf_context, font_paths, layout.font_size())?;
+            preview_metrics = Metrics::from_window_dimensions(layout.preview.size());
+            preview_fonts = theme.load_fonts(ttf_context, font_paths, preview_metrics.text_size)?;
+            preview_texture = None;
+            resized_at = None;
+        }
+
+        if editor.preview_stale || preview_texture.is_none() {
+            // pictures are cached by clue number, and every edit changes some text
+            textures.clear();
+            let mut texture = texture_creator
+                .create_texture_target(None, preview_metrics.width, preview_metrics.height)
+                .map_err(|e| e.to_string())?;
+            editor.draw_preview(
+                &mut canvas,
+                &mut texture,
+                &mut textures,
+                theme,
+                &preview_fonts,
+                &preview_metrics,
+            )?;
+            preview_texture = Some(texture);
+            editor.preview_stale = false;
+        }
+
+        canvas.set_draw_color(BACKGROUND_COLOUR);
+        canvas.clear();
+        if let Some(ref texture) = preview_texture {
+            canvas.copy(texture, None, layout.preview)?;
+        }
+        let mut form = Form {
+            canvas: &mut canvas,
+            texture_creator: &texture_creator,
+            font: &form_font,
+            layout: &layout,
+        };
+        form.draw(&editor)?;
+        canvas.present();
+    }
+
+    Ok(())
+}
+
+/// Where everything goes in the editor window: the fields down the left, and the preview with
+/// the keys under it on the right
+struct Layout {
+    size: (u32, u32),
+    form: Rect,
+    preview: Rect,
+    line_height: i32,
+}
+
+impl Layout {
+    fn new(size: (u32, u32)) -> Self {
+        let (width, height) = size;
+        let line_height = (height / 24).max(1) as i32;
+        let margin = line_height / 2;
+        let form_width = width * 2 / 5;
+
+        // a very narrow window leaves nothing for the preview, but mustn't underflow
+        let preview_width = width.saturating_sub(form_width + 2 * margin as u32);
+        let preview_height = preview_width * 9 / 16;
+        Layout {
+            size,
+            form: Rect::new(
+                margin,
+                margin,
+                form_width.saturating_sub(margin as u32).max(1),
+                height.max(1),
+            ),
+            preview: Rect::new(
+                form_width as i32 + margin,
+                margin,
+                preview_width.max(1),
+                preview_height.max(1),
+            ),
+            line_height,
+        }
+    }
+
+    fn font_size(&self) -> u16 {
+        (self.line_height * 3 / 4).max(1) as u16
+    }
+}
+
+/// The form of fields, status and hints, drawn as plain text with the markup showing
+struct Form<'a, 'ttf> {
+    canvas: &'a mut Canvas<Window>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    font: &'a FontChain<'ttf>,
+    layout: &'a Layout,
+}
+
+impl<'a, 'ttf> Form<'a, 'ttf> {
+    fn draw(&mut self, editor: &Editor) -> Result<(), String> {
+        let form = self.layout.form;
+        let line_height = self.layout.line_height;
+        let mut y = form.y();
+
+        let question = editor.current();
+        let question_type = match question.question_type {
+            QuestionType::Connection => "connection",
+            QuestionType::Sequence => "sequence",
+        };
+        let heading = format!(
+            "Question {} of {}, a {}{}",
+            editor.question + 1,
+            editor.question_set.questions.len(),
+            question_type,
+            if editor.unsaved { " (unsaved)" } else { "" }
+        );
+        self.draw_text(&heading, TEXT_COLOUR, form.x(), y, form.width())?;
+        y += line_height * 3 / 2;
+
+        let label_width = editor
+            .fields()
+            .iter()
+            .map(|&field| self.font.size_of(&editor.label(field)).map(|size| size.0))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            + line_height as u32 / 2;
+        let value_x = form.x() + label_width as i32;
+        let value_width = form.width().saturating_sub(label_width);
+
+        for field in editor.fields() {
+            self.draw_text(&editor.label(field), LABEL_COLOUR, form.x(), y, label_width)?;
+            let value_rect = Rect::new(value_x, y, value_width.max(1), line_height as u32);
+            if field == editor.field {
+                self.draw_field(&editor.buffer, editor.cursor, value_rect)?;
+            } else {
+                self.draw_text(
+                    &editor.field_text(field),
+                    TEXT_COLOUR,
+                    value_x,
+                    y,
+                    value_width,
+                )?;
+            }
+            y += line_height;
+        }
+
+        y += line_height / 2;
+        self.draw_text(&editor.status, STATUS_COLOUR, form.x(), y, form.width())?;
+
+        let preview = self.layout.preview;
+        let mut y = preview.bottom() + line_height / 2;
+        for hint in HINTS.iter() {
+            self.draw_text(hint, HINT_COLOUR, preview.x(), y, preview.width())?;
+            y += line_height;
+        }
+
+        Ok(())
+    }
+
+    /// Draws the selected field, scrolled so the cursor is always in view
+    fn draw_field(&mut self, text: &str, cursor: usize, rect: Rect) -> Result<(), String> {
+        self.canvas.set_draw_color(FIELD_COLOUR);
+        self.canvas.fill_rect(rect)?;
+
+        let cursor_x = self.font.size_of(&text[..cursor])?.0 as i32;
+        let scroll = (cursor_x - rect.width() as i32 + 2).max(0);
+
+        self.canvas.set_clip_rect(rect);
+        self.draw_text(text, TEXT_COLOUR, rect.x() - scroll, rect.y(), u32::MAX)?;
+        self.canvas.set_draw_color(TEXT_COLOUR);
+        self.canvas.fill_rect(Rect::new(
+            rect.x() + cursor_x - scroll,
+            rect.y(),
+            2,
+            rect.height(),
+        ))?;
+        self.canvas.set_clip_rect(None);
+        Ok(())
+    }
+
+    /// Draws a line of text, cut off at a width
+    fn draw_text(
+        &mut self,
+        text: &str,
+        colour: Color,
+        x: i32,
+        y: i32,
+        width: u32,
+    ) -> Result<(), String> {
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let surface = self.font.render(text, colour)?;
+        let texture = self
+            .texture_creator
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+        let shown_width = surface.width().min(width);
+        self.canvas.copy(
+            &texture,
+            Rect::new(0, 0, shown_width, surface.height()),
+            Rect::new(x, y, shown_width, surface.height()),
+        )
+    }
+}
+
+impl Editor {
+    /// Starts editing a question file, or a new one if it doesn't exist
+    fn load(path: &str) -> Result<Self, Vec<String>> {
+        let path = PathBuf::from(path);
+        if questions::is_spreadsheet(&path) {
+            return Err(vec![format!(
+                "{} is a spreadsheet, which can't be edited here. Use import to make it into a question file first.",
+                path.display()
+            )]);
+        }
+
+        let mut problems = vec![];
+        let mut question_set = if path.exists() {
+            let (question_set, errors) = questions::load_questions_partially(&path)?;
+            problems = errors;
+            question_set
+        } else {
+            let title = path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().replace('_', " "));
+            QuestionSet {
+                title,
+                questions: vec![],
+            }
+        };
+        if question_set.questions.is_empty() {
+            question_set
+                .questions
+                .push(blank_question(QuestionType::Connection));
+        }
+        // every question gets four clues to edit, however many it had
+        for question in &mut question_set.questions {
+            if question.clues.len() > 4 {
+                problems.push(format!(
+                    "Only the first 4 clues for {} are kept",
+                    question.connection
+                ));
+                question.clues.truncate(4);
+            }
+            while question.clues.len() < 4 {
+                question.clues.push(Clue::TextClue(String::new()));
+            }
+        }
+
+        // lines that couldn't be read are lost when the file is saved, so they're all listed
+        for problem in &problems {
+            eprintln!("{}: {}", path.display(), problem);
+        }
+        let status = match problems.len() {
+            0 => String::new(),
+            1 => format!("Problem loading the file: {}", problems[0]),
+            n => format!(
+                "{} problems loading the file, listed in the terminal. The first: {}",
+                n, problems[0]
+            ),
+        };
+
+        let mut editor = Editor {
+            path,
+            question_set,
+            question: 0,
+            field: Field::Connection,
+            buffer: String::new(),
+            cursor: 0,
+            status,
+            unsaved: false,
+            quit_warned: false,
+            preview_stale: true,
+        };
+        editor.select(Field::Connection);
+        Ok(editor)
+    }
+
+    fn current(&self) -> &Question {
+        &self.question_set.questions[self.question]
+    }
+
+    fn current_mut(&mut self) -> &mut Question {
+        &mut self.question_set.questions[self.question]
+    }
+
+    fn question_type(&self) -> QuestionType {
+        self.current().question_type
+    }
+
+    /// The fields of the current question, in order. There's always an empty note at the end to
+    /// add another.
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field::Title, Field::Connection];
+        for i in 0..4 {
+            fields.push(Field::Clue(i));
+            fields.push(Field::Picture(i));
+        }
+        fields.extend((0..=self.current().notes.len()).map(Field::Note));
+        fields.extend([Field::Author, Field::Difficulty, Field::Tags]);
+        fields
+    }
+
+    fn label(&self, field: Field) -> String {
+        match field {
+            Field::Title => "Set title".into(),
+            Field::Connection => match self.question_type() {
+                QuestionType::Connection => "Connection".into(),
+                QuestionType::Sequence => "Sequence".into(),
+            },
+            Field::Clue(i) => format!("Clue {}", i + 1),
+            Field::Picture(i) => format!("  Picture {}", i + 1),
+            Field::Note(i) => format!("Note {}", i + 1),
+            Field::Author => "Author".into(),
+            Field::Difficulty => "Difficulty".into(),
+            Field::Tags => "Tags".into(),
+        }
+    }
+
+    fn field_text(&self, field: Field) -> String {
+        let question = self.current();
+        match field {
+            Field::Title => self.question_set.title.clone(),
+            Field::Connection => question.connection.clone(),
+            Field::Clue(i) => question.clues[i].text().into(),
+            Field::Picture(i) => match question.clues[i] {
+                Clue::PictureClue(ref picture, _) => picture.path.clone(),
+                Clue::TextClue(_) => String::new(),
+            },
+            Field::Note(i) => question.notes.get(i).cloned().unwrap_or_default(),
+            Field::Author => question.metadata.author.clone().unwrap_or_default(),
+            Field::Difficulty => question.metadata.difficulty.clone().unwrap_or_default(),
+            Field::Tags => question.metadata.tags.join(", "),
+        }
+    }
+
+    /// Puts the text of the selected field back into the question
+    fn apply(&mut self) {
+        let text = self.buffer.clone();
+        self.changed();
+        let field = self.field;
+        let question = &mut self.question_set.questions[self.question];
+        match field {
+            Field::Title => self.question_set.title = text,
+            Field::Connection => question.connection = text,
+            Field::Clue(i) => match question.clues[i] {
+                Clue::TextClue(ref mut clue) => *clue = text,
+                Clue::PictureClue(_, ref mut caption) => *caption = text,
+            },
+            Field::Picture(i) => self.set_picture(i, &text),
+            Field::Note(i) => {
+                if i < question.notes.len() {
+                    question.notes[i] = text;
+                } else if !text.trim().is_empty() {
+                    question.notes.push(text);
+                }
+            }
+            Field::Author => question.metadata.author = Some(text).filter(|a| !a.is_empty()),
+            Field::Difficulty => {
+                question.metadata.difficulty = Some(text).filter(|d| !d.is_empty())
+            }
+            Field::Tags => question.metadata.tags = parse_tags(&text),
+        }
+    }
+
+    /// Marks the questions as edited since they were last saved
+    fn changed(&mut self) {
+        self.unsaved = true;
+        self.quit_warned = false;
+        self.preview_stale = true;
+    }
+
+    /// Makes a clue a picture clue, or a text clue if there's no path. The picture is only
+    /// changed once the path is to an image that loads, so typing a path doesn't lose the
+    /// picture that was there.
+    fn set_picture(&mut self, clue: usize, path: &str) {
+        let path = path.trim();
+        let current = &mut self.current_mut().clues[clue];
+        let caption = current.text().to_string();
+
+        if path.is_empty() {
+            *current = Clue::TextClue(caption);
+            self.status.clear();
+        } else if !Path::new(path).is_file() {
+            self.status = format!("No image at {}", path);
+        } else if !matches!(current, Clue::PictureClue(ref picture, _) if picture.path == path) {
+            match Picture::load(path) {
+                Ok(picture) => {
+                    *current = Clue::PictureClue(picture, caption);
+                    self.status.clear();
+                }
+                Err(e) => self.status = format!("Could not load image {}: {}", path, e),
+            }
+        }
+    }
+
+    fn select(&mut self, field: Field) {
+        self.field = field;
+        self.buffer = self.field_text(field);
+        self.cursor = self.buffer.len();
+    }
+
+    fn move_field(&mut self, offset: isize) {
+        let fields = self.fields();
+        let index = fields.iter().position(|&f| f == self.field).unwrap_or(0) as isize;
+        let index = (index + offset).clamp(0, fields.len() as isize - 1) as usize;
+        self.select(fields[index]);
+    }
+
+    fn move_question(&mut self, offset: isize) {
+        let count = self.question_set.questions.len() as isize;
+        let question = (self.question as isize + offset).clamp(0, count - 1) as usize;
+        if question != self.question {
+            self.tidy_notes();
+            self.question = question;
+            self.preview_stale = true;
+            // the question might have fewer notes
+            let field = match self.field {
+                Field::Note(_) => Field::Note(0),
+                field => field,
+            };
+            self.select(field);
+        }
+    }
+
+    fn tidy_notes(&mut self) {
+        self.current_mut()
+            .notes
+            .retain(|note| !note.trim().is_empty());
+    }
+
+    fn new_question(&mut self) {
+        self.tidy_notes();
+        let question = blank_question(self.question_type());
+        self.question += 1;
+        self.question_set.questions.insert(self.question, question);
+        self.changed();
+        self.select(Field::Connection);
+    }
+
+    fn delete_question(&mut self) {
+        let questions = &mut self.question_set.questions;
+        questions.remove(self.question);
+        if questions.is_empty() {
+            questions.push(blank_question(QuestionType::Connection));
+        }
+        self.question = self.question.min(questions.len() - 1);
+        self.changed();
+        self.status = "Deleted the question".into();
+        self.select(Field::Connection);
+    }
+
+    fn switch_type(&mut self) {
+        let question = self.current_mut();
+        question.question_type = match question.question_type {
+            QuestionType::Connection => QuestionType::Sequence,
+            QuestionType::Sequence => QuestionType::Connection,
+        };
+        self.changed();
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.buffer.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        self.apply();
+    }
+
+    /// Deletes the character before the cursor, or after it
+    fn delete(&mut self, direction: isize) {
+        let start = if direction < 0 {
+            self.previous_boundary()
+        } else {
+            self.cursor
+        };
+        let end = if direction < 0 {
+            self.cursor
+        } else {
+            self.next_boundary()
+        };
+        if start < end {
+            self.buffer.replace_range(start..end, "");
+            self.cursor = start;
+            self.apply();
+        }
+    }
+
+    fn move_cursor(&mut self, direction: isize) {
+        self.cursor = if direction < 0 {
+            self.previous_boundary()
+        } else {
+            self.next_boundary()
+        };
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.buffer[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.buffer[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Uses a dropped file as the picture for the selected clue
+    fn drop_picture(&mut self, filename: &str) {
+        let clue = match self.field {
+            Field::Clue(i) | Field::Picture(i) => i,
+            _ => {
+                self.status = "Select a clue to drop a picture on".into();
+                return;
+            }
+        };
+
+        // question files are usually next to their pictures, so keep paths short where possible
+        let path = Path::new(filename);
+        let path = std::env::current_dir()
+            .ok()
+            .and_then(|dir| path.strip_prefix(dir).ok())
+            .unwrap_or(path);
+
+        self.select(Field::Picture(clue));
+        self.buffer = path.to_string_lossy().into_owned();
+        self.cursor = self.buffer.len();
+        self.apply();
+    }
+
+    /// Quits unless there are unsaved changes, which need asking twice
+    fn request_quit(&mut self) -> bool {
+        if !self.unsaved || self.quit_warned {
+            return true;
+        }
+        self.quit_warned = true;
+        self.status =
+            "There are unsaved changes. Quit again to lose them, or Ctrl+S to save.".into();
+        false
+    }
+
+    /// Checks the questions can be written as a question file and read back the same, so saving
+    /// never writes a file that won't load
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        for (n, question) in self.question_set.questions.iter().enumerate() {
+            if question.connection.trim().is_empty() {
+                problems.push(format!("Question {} has no connection", n + 1));
+            }
+            for (i, clue) in question.clues.iter().enumerate() {
+                if clue.text().trim().is_empty() {
+                    problems.push(format!("Question {} has no clue {}", n + 1, i + 1));
+                } else if let Some(problem) = questions::clue_problem(clue) {
+                    problems.push(format!("Question {} clue {} {}", n + 1, i + 1, problem));
+                }
+            }
+        }
+        problems
+    }
+
+    fn save(&mut self) {
+        let problems = self.problems();
+        if let Some(problem) = problems.first() {
+            self.status = if problems.len() > 1 {
+                format!("Not saved: {}, and {} more", problem, problems.len() - 1)
+            } else {
+                format!("Not saved: {}", problem)
+            };
+            return;
+        }
+
+        self.tidy_notes();
+        for question in &mut self.question_set.questions {
+            let metadata = &mut question.metadata;
+            for value in [&mut metadata.author, &mut metadata.difficulty] {
+                *value = value
+                    .take()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty());
+            }
+        }
+        // the selected field might have been an empty note, which is gone now
+        self.select(match self.field {
+            Field::Note(_) => Field::Note(0),
+            field => field,
+        });
+
+        match questions::save_questions(&self.path, &self.question_set) {
+            Ok(()) => {
+                self.unsaved = false;
+                self.status = format!(
+                    "Saved {} questions to {}",
+                    self.question_set.questions.len(),
+                    self.path.display()
+                );
+            }
+            Err(e) => self.status = format!("Could not save {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Renders the current question's answer screen, as the game draws it
+    fn draw_preview(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+        textures: &mut TextureCache<'_, WindowContext>,
+        theme: &Theme,
+        fonts: &ElementFonts,
+        metrics: &Metrics,
+    ) -> Result<(), String> {
+        // the game takes the question to play it, so it's lent to it and taken back
+        let question_type = self.question_type();
+        let question = std::mem::replace(
+            self.current_mut(),
+            Question::new(question_type, String::new()),
+        );
+        let metadata = question.metadata.clone();
+
+        let started = Instant::now();
+        let mut question_state = QuestionState::new_question(question, true, started);
+        if let QuestionState::Question {
+            ref mut stopped, ..
+        } = question_state
+        {
+            *stopped = Some(started);
+        }
+        let phase = preview::question_phase(&mut question_state);
+        phase.next();
+        phase.show_answer();
+
+        let result = canvas
+            .with_texture_canvas(texture, |preview_canvas| {
+                preview_canvas.set_blend_mode(BlendMode::None);
+                crate::draw_screen(
+                    preview_canvas,
+                    textures,
+                    theme,
+                    fonts,
+                    metrics,
+                    &question_state,
+                    (0, 0),
+                    &AnimationFrame::settled(),
+                )
+            })
+            .map_err(|e| e.to_string());
+
+        let mut question = question_state.into_question().unwrap();
+        question.metadata = metadata;
+        *self.current_mut() = question;
+        result
+    }
+}
+
+fn blank_question(question_type: QuestionType) -> Question {
+    let mut question = Question::new(question_type, String::new());
+    question.clues = (0..4).map(|_| Clue::TextClue(String::new())).collect();
+    question
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_fits_in_any_window() {
+        for &(width, height) in &[(1280, 720), (80, 1440), (1, 1), (0, 0), (3000, 40)] {
+            let layout = Layout::new((width, height));
+            assert!(layout.form.width() >= 1 && layout.form.height() >= 1);
+            assert!(layout.preview.width() >= 1 && layout.preview.height() >= 1);
+            if width >= 100 {
+                assert!(layout.preview.right() as u32 <= width);
+            }
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use std::cell::OnceCell;
+use std::fs::File;
+use std::path::Path;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::surface::Surface;
+use sdl2::ttf::{Font, FontStyle, Sdl2TtfContext};
+use serde::Deserialize;
+
+use crate::markup::{Line, Script, Span};
+
+const DEFAULT_FONT_PATH: &str = "fonts/Roboto-Regular.ttf";
+/// Size to open fonts at when checking they can be loaded
+const CHECK_SIZE: u16 = 12;
+
+/// Text is never shrunk below this fraction of its full size
+const MIN_SCALE: f32 = 0.4;
+/// Each smaller size is this fraction of the one before
+const SCALE_STEP: f32 = 0.9;
+
+/// Superscripts and subscripts are this fraction of the size of the text around them
+const SCRIPT_SCALE: f32 = 0.6;
+/// How far superscripts are raised, and subscripts lowered, as fractions of the font's ascent
+const SUPERSCRIPT_RISE: f32 = 0.45;
+const SUBSCRIPT_DROP: f32 = 0.2;
+
+#[derive(Debug, Deserialize)]
+struct FontsFile {
+    fonts: Vec<String>,
+}
+
+/// Loads the list of font paths, main font first. Fallback fonts that don't exist are left out,
+/// so one list can cover several platforms, and ones that can't be opened are left out with a
+/// warning. If the file doesn't exist only the built-in font is used.
+pub fn load_font_list<P: AsRef<Path>>(
+    ttf_context: &Sdl2TtfContext,
+    path: P,
+) -> Result<Vec<String>, Vec<String>> {
+    let font_paths = match File::open(path) {
+        Ok(file) => {
+            let fonts_file: FontsFile =
+                serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?;
+            fonts_file.fonts
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![DEFAULT_FONT_PATH.into()],
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+
+    let (main_path, fallback_paths) = match font_paths.split_first() {
+        Some(paths) => paths,
+        None => return Err(vec!["No fonts listed".into()]),
+    };
+    if let Err(e) = ttf_context.load_font(main_path, CHECK_SIZE) {
+        return Err(vec![format!("Could not load font {}: {}", main_path, e)]);
+    }
+
+    let mut usable_paths = vec![main_path.clone()];
+    for path in fallback_paths {
+        if !Path::new(path).exists() {
+            continue;
+        }
+        match ttf_context.load_font(path, CHECK_SIZE) {
+            Ok(_) => usable_paths.push(path.clone()),
+            Err(e) => eprintln!("Could not load fallback font {}, skipping it: {}", path, e),
+        }
+    }
+
+    Ok(usable_paths)
+}
+
+/// A font along with fallbacks for any characters it doesn't have, all at the same size. Text is
+/// measured and rendered a run of characters at a time, each run in the first font that has its
+/// characters. Bold and italic text uses styled copies of the main font, while fallbacks are
+/// only ever plain.
+pub struct FontChain<'ttf> {
+    // main font first
+    fonts: Vec<Font<'ttf, 'static>>,
+    styled_main_fonts: Vec<(FontStyle, Font<'ttf, 'static>)>,
+}
+
+impl<'ttf> FontChain<'ttf> {
+    pub fn load(
+        ttf_context: &'ttf Sdl2TtfContext,
+        paths: &[String],
+        size: u16,
+    ) -> Result<Self, String> {
+        let fonts = paths
+            .iter()
+            .map(|path| ttf_context.load_font(path, size))
+            .collect::<Result<_, _>>()?;
+
+        let mut styled_main_fonts = vec![];
+        for &style in &[
+            FontStyle::BOLD,
+            FontStyle::ITALIC,
+            FontStyle::BOLD | FontStyle::ITALIC,
+        ] {
+            let mut font = ttf_context.load_font(&paths[0], size)?;
+            font.set_style(style);
+            styled_main_fonts.push((style, font));
+        }
+
+        Ok(FontChain {
+            fonts,
+            styled_main_fonts,
+        })
+    }
+
+    pub fn ascent(&self) -> i32 {
+        self.fonts[0].ascent()
+    }
+
+    pub fn recommended_line_spacing(&self) -> i32 {
+        self.fonts[0].recommended_line_spacing()
+    }
+
+    pub fn size_of(&self, text: &str) -> Result<(u32, u32), String> {
+        self.size_of_styled(text, FontStyle::NORMAL)
+    }
+
+    pub fn size_of_styled(&self, text: &str, style: FontStyle) -> Result<(u32, u32), String> {
+        let mut size = (0, 0);
+        for (font, run) in self.runs(text, style) {
+            let (width, height) = font.size_of(run).map_err(|e| e.to_string())?;
+            size = (size.0 + width, size.1.max(height));
+        }
+        Ok(size)
+    }
+
+    /// Renders a single line of text, with the runs in different fonts lined up on a common
+    /// baseline
+    pub fn render(&self, text: &str, colour: Color) -> Result<Surface<'static>, String> {
+        self.render_styled(text, FontStyle::NORMAL, colour)
+            .map(|(surface, _)| surface)
+    }
+
+    /// Renders a single line of text in a style, along with how far down the surface its
+    /// baseline is
+    pub fn render_styled(
+        &self,
+        text: &str,
+        style: FontStyle,
+        colour: Color,
+    ) -> Result<(Surface<'static>, i32), String> {
+        let runs = self.runs(text, style);
+        if let [(font, run)] = runs[..] {
+            let surface = font
+                .render(run)
+                .blended(colour)
+                .map_err(|e| e.to_string())?;
+            return Ok((surface, font.ascent()));
+        }
+
+        let ascent = runs.iter().map(|(f, _)| f.ascent()).max().unwrap_or(0);
+        let mut rendered_runs = vec![];
+        for (font, run) in runs {
+            let mut surface = font
+                .render(run)
+                .blended(colour)
+                .map_err(|e| e.to_string())?;
+            // runs don't overlap, so copy them exactly rather than blending
+            surface.set_blend_mode(BlendMode::None)?;
+            rendered_runs.push((ascent - font.ascent(), surface));
+        }
+
+        let width = rendered_runs.iter().map(|(_, s)| s.width()).sum();
+        let height = rendered_runs
+            .iter()
+            .map(|(y, s)| *y as u32 + s.height())
+            .max()
+            .unwrap_or(0);
+        let mut line_surface = Surface::new(width, height, PixelFormatEnum::ARGB8888)?;
+
+        let mut x = 0;
+        for (y, surface) in rendered_runs {
+            let dst_rect = Rect::new(x, y, surface.width(), surface.height());
+            surface.blit(None, &mut line_surface, dst_rect)?;
+            x += surface.width() as i32;
+        }
+
+        Ok((line_surface, ascent))
+    }
+
+    fn font_for(&self, ch: char, style: FontStyle) -> &Font<'ttf, 'static> {
+        if ch as u32 > 0xffff {
+            // SDL_ttf can only look up characters in the Basic Multilingual Plane, so anything
+            // beyond it (most emoji and flags) goes to the last font
+            return self.fonts.last().unwrap();
+        }
+
+        let font = self
+            .fonts
+            .iter()
+            .find(|font| font.find_glyph(ch).is_some())
+            .unwrap_or(&self.fonts[0]);
+
+        if std::ptr::eq(font, &self.fonts[0]) {
+            if let Some((_, styled)) = self.styled_main_fonts.iter().find(|(s, _)| *s == style) {
+                return styled;
+            }
+        }
+        font
+    }
+
+    /// Splits text into runs of characters that use the same font
+    fn runs<'a>(&self, text: &'a str, style: FontStyle) -> Vec<(&Font<'ttf, 'static>, &'a str)> {
+        let mut runs = vec![];
+        let mut run_start = 0;
+        let mut run_font: Option<&Font<'ttf, 'static>> = None;
+
+        for (i, ch) in text.char_indices() {
+            let font = self.font_for(ch, style);
+            match run_font {
+                Some(current) if std::ptr::eq(current, font) => {}
+                Some(current) => {
+                    runs.push((current, &text[run_start..i]));
+                    run_start = i;
+                    run_font = Some(font);
+                }
+                None => run_font = Some(font),
+            }
+        }
+        if let Some(current) = run_font {
+            runs.push((current, &text[run_start..]));
+        }
+
+        runs
+    }
+}
+
+/// A font chain at one size, along with a smaller one for superscripts and subscripts
+#[derive(Copy, Clone)]
+pub struct SizedFont<'f, 'ttf> {
+    pub normal: &'f FontChain<'ttf>,
+    script: &'f FontChain<'ttf>,
+}
+
+impl<'f, 'ttf> SizedFont<'f, 'ttf> {
+    /// A font for text without superscripts or subscripts
+    pub fn plain(font: &'f FontChain<'ttf>) -> Self {
+        SizedFont {
+            normal: font,
+            script: font,
+        }
+    }
+
+    pub fn recommended_line_spacing(&self) -> i32 {
+        self.normal.recommended_line_spacing()
+    }
+
+    fn chain(&self, script: Script) -> &'f FontChain<'ttf> {
+        match script {
+            Script::Normal => self.normal,
+            Script::Super | Script::Sub => self.script,
+        }
+    }
+
+    pub fn width_of(&self, spans: &[Span]) -> Result<u32, String> {
+        let mut width = 0;
+        for span in spans {
+            let chain = self.chain(span.style.script);
+            width += chain.size_of_styled(&span.text, span.style.font_style())?.0;
+        }
+        Ok(width)
+    }
+
+    /// Renders a line of styled text, with superscripts raised and subscripts lowered from the
+    /// common baseline. Returns the surface along with how far down it the baseline is.
+    pub fn render(&self, line: &[Span], colour: Color) -> Result<(Surface<'static>, i32), String> {
+        let ascent = self.normal.ascent();
+
+        let mut rendered_spans = vec![];
+        for span in line.iter().filter(|span| !span.text.is_empty()) {
+            let (mut surface, baseline) = self.chain(span.style.script).render_styled(
+                &span.text,
+                span.style.font_style(),
+                colour,
+            )?;
+            let shift = match span.style.script {
+                Script::Normal => 0,
+                Script::Super => -(ascent as f32 * SUPERSCRIPT_RISE) as i32,
+                Script::Sub => (ascent as f32 * SUBSCRIPT_DROP) as i32,
+            };
+            surface.set_blend_mode(BlendMode::None)?;
+            // where the top of the span is relative to the line's baseline
+            rendered_spans.push((shift - baseline, surface));
+        }
+
+        if let [(top, _)] = rendered_spans[..] {
+            let (_, surface) = rendered_spans.pop().unwrap();
+            return Ok((surface, -top));
+        }
+
+        let top = rendered_spans
+            .iter()
+            .map(|(y, _)| *y)
+            .fold(-ascent, i32::min);
+        let width = rendered_spans.iter().map(|(_, s)| s.width()).sum::<u32>();
+        let height = rendered_spans
+            .iter()
+            .map(|(y, s)| (y - top) as u32 + s.height())
+            .max()
+            .unwrap_or(0);
+        let mut line_surface =
+            Surface::new(width.max(1), height.max(1), PixelFormatEnum::ARGB8888)?;
+
+        let mut x = 0;
+        for (y, surface) in rendered_spans {
+            let dst_rect = Rect::new(x, y - top, surface.width(), surface.height());
+            surface.blit(None, &mut line_surface, dst_rect)?;
+            x += surface.width() as i32;
+        }
+
+        Ok((line_surface, -top))
+    }
+}
+
+/// A font chain at its full size and a range of smaller ones, so that long text can be shrunk
+/// until it fits. Only the full size is loaded up front, and each smaller one the first time it's
+/// needed.
+pub struct ScalableFont<'ttf> {
+    ttf_context: &'ttf Sdl2TtfContext,
+    paths: Vec<String>,
+    // largest first
+    sizes: Vec<(u16, OnceCell<FontChain<'ttf>>)>,
+}
+
+/// How a piece of text fits into a box
+pub struct TextFit<'f, 'ttf> {
+    pub font: SizedFont<'f, 'ttf>,
+    /// The font size used as a fraction of the full size
+    pub scale: f32,
+    /// Whether some words are too long for the box even at the smallest size, so have to be
+    /// broken across lines
+    pub words_broken: bool,
+}
+
+impl<'ttf> ScalableFont<'ttf> {
+    pub fn load(
+        ttf_context: &'ttf Sdl2TtfContext,
+        paths: &[String],
+        full_size: u16,
+    ) -> Result<Self, String> {
+        let min_size = ((full_size as f32 * MIN_SCALE) as u16).max(1);
+
+        let mut sizes = vec![];
+        let mut size = full_size;
+        while size >= min_size {
+            sizes.push((size, OnceCell::new()));
+            // always go down by at least one point
+            size = ((size as f32 * SCALE_STEP) as u16).min(size - 1);
+        }
+        let full = FontChain::load(ttf_context, paths, full_size)?;
+        let _ = sizes[0].1.set(full);
+
+        Ok(ScalableFont {
+            ttf_context,
+            paths: paths.to_vec(),
+            sizes,
+        })
+    }
+
+    pub fn main_path(&self) -> &str {
+        &self.paths[0]
+    }
+
+    pub fn full_size(&self) -> &FontChain<'ttf> {
+        self.sizes[0].1.get().unwrap()
+    }
+
+    /// The font chain at one of its sizes, loading it if it hasn't been used before
+    fn chain(&self, index: usize) -> Result<&FontChain<'ttf>, String> {
+        let (size, chain) = &self.sizes[index];
+        if let Some(chain) = chain.get() {
+            return Ok(chain);
+        }
+        let loaded = FontChain::load(self.ttf_context, &self.paths, *size)?;
+        Ok(chain.get_or_init(|| loaded))
+    }
+
+    /// The font at one of its sizes, with the size used for its superscripts and subscripts
+    fn sized(&self, index: usize) -> Result<SizedFont<'_, 'ttf>, String> {
+        let script_size = (self.sizes[index].0 as f32 * SCRIPT_SCALE) as u16;
+        let script_index = self
+            .sizes
+            .iter()
+            .position(|(size, _)| *size <= script_size)
+            .unwrap_or(self.sizes.len() - 1);
+        Ok(SizedFont {
+            normal: self.chain(index)?,
+            script: self.chain(script_index)?,
+        })
+    }
+
+    /// The largest size at which the text fits in the box without breaking any words. If it
+    /// doesn't fit at any size, the smallest size is used.
+    pub fn fit(
+        &self,
+        paragraphs: &[Line],
+        width: u32,
+        height: u32,
+    ) -> Result<TextFit<'_, 'ttf>, String> {
+        let full_size = self.sizes[0].0 as f32;
+        let words_fit = |font: SizedFont| {
+            paragraphs
+                .iter()
+                .flat_map(|paragraph| crate::markup::words(paragraph))
+                .all(|word| font.width_of(&word.spans).unwrap() <= width)
+        };
+
+        for (i, (size, _)) in self.sizes.iter().enumerate() {
+            let font = self.sized(i)?;
+            let lines = crate::wrap_text(paragraphs, font, width).len() as i32;
+
+            if words_fit(font) && lines * font.recommended_line_spacing() <= height as i32 {
+                return Ok(TextFit {
+                    font,
+                    scale: *size as f32 / full_size,
+                    words_broken: false,
+                });
+            }
+        }
+
+        let font = self.sized(self.sizes.len() - 1)?;
+        Ok(TextFit {
+            font,
+            scale: self.sizes.last().unwrap().0 as f32 / full_size,
+            words_broken: !words_fit(font),
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::joystick::Joystick;
+use sdl2::{GameControllerSubsystem, JoystickSubsystem, Sdl};
+
+use crate::buzzers::Team;
+use crate::controls::{Action, Controls};
+
+/// Extra controller mappings, in the format of https://github.com/gabomdq/SDL_GameControllerDB
+const CONTROLLER_MAPPINGS_PATH: &str = "./gamecontrollerdb.txt";
+
+/// Keeps track of the game controllers and joysticks that are plugged in. Devices SDL recognises
+/// as game controllers use the named button mapping; anything else is opened as a plain joystick
+/// and uses the numbered button mapping.
+///
+/// Each device gets the lowest free slot number (starting from 1) when it's plugged in, which is
+/// how a device is assigned to a team as a buzzer.
+pub struct Gamepads {
+    controller_subsystem: GameControllerSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    // keyed by joystick instance id
+    controllers: HashMap<u32, (u32, GameController)>,
+    joysticks: HashMap<u32, (u32, Joystick)>,
+}
+
+impl Gamepads {
+    pub fn new(sdl_context: &Sdl) -> Result<Self, String> {
+        let controller_subsystem = sdl_context.game_controller()?;
+        let joystick_subsystem = sdl_context.joystick()?;
+
+        if std::path::Path::new(CONTROLLER_MAPPINGS_PATH).exists() {
+            if let Err(e) = controller_subsystem.load_mappings(CONTROLLER_MAPPINGS_PATH) {
+                eprintln!("Could not load {}: {}", CONTROLLER_MAPPINGS_PATH, e);
+            }
+        }
+
+        // SDL sends a device added event for everything that's already plugged in, so there's
+        // no need to open anything here
+        Ok(Gamepads {
+            controller_subsystem,
+            joystick_subsystem,
+            controllers: HashMap::new(),
+            joysticks: HashMap::new(),
+        })
+    }
+
+    /// Opens and closes devices as they are plugged in and out, and translates button presses
+    /// into actions
+    pub fn handle_event(&mut self, event: &Event, controls: &Controls) -> Option<Action> {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                match self.controller_subsystem.open(which) {
+                    Ok(controller) => {
+                        let slot = self.free_slot();
+                        println!("Controller {} connected: {}", slot, controller.name());
+                        self.controllers
+                            .insert(controller.instance_id(), (slot, controller));
+                    }
+                    Err(e) => eprintln!("Could not open controller {}: {}", which, e),
+                }
+                None
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some((slot, controller)) = self.controllers.remove(&which) {
+                    println!("Controller {} disconnected: {}", slot, controller.name());
+                }
+                None
+            }
+            Event::JoyDeviceAdded { which, .. } => {
+                // game controllers are joysticks too, but they're handled above
+                if !self.controller_subsystem.is_game_controller(which) {
+                    match self.joystick_subsystem.open(which) {
+                        Ok(joystick) => {
+                            let slot = self.free_slot();
+                            println!("Joystick {} connected: {}", slot, joystick.name());
+                            self.joysticks
+                                .insert(joystick.instance_id(), (slot, joystick));
+                        }
+                        Err(e) => eprintln!("Could not open joystick {}: {}", which, e),
+                    }
+                }
+                None
+            }
+            Event::JoyDeviceRemoved { which, .. } => {
+                if let Some((slot, joystick)) = self.joysticks.remove(&which) {
+                    println!("Joystick {} disconnected: {}", slot, joystick.name());
+                }
+                None
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                let (slot, _) = self.controllers.get(&which)?;
+                match controls.buzzer_team_for_slot(*slot) {
+                    // every button on a buzzer buzzes
+                    Some(team) => Some(buzz_action(team)),
+                    None => controls.action_for_controller_button(button),
+                }
+            }
+            Event::JoyButtonDown {
+                which, button_idx, ..
+            } => {
+                let (slot, _) = self.joysticks.get(&which)?;
+                match controls.buzzer_team_for_slot(*slot) {
+                    Some(team) => Some(buzz_action(team)),
+                    None => controls.action_for_joystick_button(button_idx),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn free_slot(&self) -> u32 {
+        let used_slots: Vec<u32> = self
+            .controllers
+            .values()
+            .map(|(slot, _)| *slot)
+            .chain(self.joysticks.values().map(|(slot, _)| *slot))
+            .collect();
+
+        (1..).find(|slot| !used_slots.contains(slot)).unwrap()
+    }
+}
+
+fn buzz_action(team: Team) -> Action {
+    match team {
+        Team::Red => Action::BuzzRed,
+        Team::Blue => Action::BuzzBlue,
+    }
+}
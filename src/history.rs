@@ -0,0 +1,251 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// A game that's been played, and the questions that were shown in it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    /// The day it was played on, as YYYY-MM-DD
+    pub date: String,
+    #[serde(default)]
+    pub teams: Vec<String>,
+    /// The seed it was shuffled with, so starting it again the same day carries on with it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// IDs of the questions shown, in the order they were played
+    #[serde(default)]
+    pub questions: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct HistoryFile {
+    games: Vec<Game>,
+}
+
+/// Which questions have been played in earlier games, so they can be left out of later ones
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    games: Vec<Game>,
+    /// Teams playing this game
+    teams: Vec<String>,
+    /// The seed this game is shuffled with
+    seed: Option<u64>,
+    /// The day this game started, as YYYY-MM-DD
+    date: String,
+    /// Where this game is in the history, once it's been added. It's only added once a question
+    /// has been played, so starting the game and quitting doesn't count as a game.
+    current_game: Option<usize>,
+}
+
+/// Loads the question history. If the file doesn't exist the history starts empty, and the file
+/// is created when the first question is played.
+pub fn load_history<P: AsRef<Path>>(path: P) -> Result<History, Vec<String>> {
+    let path = path.as_ref();
+    let history_file = match File::open(path) {
+        Ok(file) => serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => HistoryFile::default(),
+        Err(e) => return Err(vec![e.to_string()]),
+    };
+
+    Ok(History {
+        path: path.to_path_buf(),
+        games: history_file.games,
+        teams: vec![],
+        seed: None,
+        date: today(),
+        current_game: None,
+    })
+}
+
+impl History {
+    /// Sets the teams for the game about to be played
+    pub fn set_teams(&mut self, teams: Vec<String>) {
+        self.teams = teams;
+    }
+
+    /// Sets the seed for the game about to be played. A game played earlier today with the same
+    /// seed is this game started again, so it doesn't count as an earlier game and is carried on
+    /// with. The same seed on another day is a new game.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    /// Whether a game in the history is the one being played
+    fn is_this_game(&self, game: &Game) -> bool {
+        self.seed.is_some() && game.seed == self.seed && game.date == self.date
+    }
+
+    /// Games played before this one
+    fn earlier_games(&self) -> impl DoubleEndedIterator<Item = &Game> {
+        self.games
+            .iter()
+            .filter(move |game| !self.is_this_game(game))
+    }
+
+    /// Whether a question was played in one of the last few games
+    pub fn used_in_last_games(&self, question_id: &str, game_count: usize) -> bool {
+        self.earlier_games()
+            .rev()
+            .take(game_count)
+            .any(|game| game.questions.iter().any(|id| id == question_id))
+    }
+
+    /// Whether any of the teams have seen a question before
+    pub fn used_by_teams(&self, question_id: &str, teams: &[String]) -> bool {
+        self.earlier_games()
+            .filter(|game| {
+                game.teams
+                    .iter()
+                    .any(|team| teams.iter().any(|t| t.eq_ignore_ascii_case(team)))
+            })
+            .any(|game| game.questions.iter().any(|id| id == question_id))
+    }
+
+    /// Records a question as played in this game, and saves the history
+    pub fn record(&mut self, question_id: &str) -> Result<(), String> {
+        let index = match self.current_game {
+            Some(index) => index,
+            None => {
+                let index = match self.games.iter().rposition(|game| self.is_this_game(game)) {
+                    Some(index) => index,
+                    None => {
+                        self.games.push(Game {
+                            date: self.date.clone(),
+                            teams: self.teams.clone(),
+                            seed: self.seed,
+                            questions: vec![],
+                        });
+                        self.games.len() - 1
+                    }
+                };
+                self.current_game = Some(index);
+                index
+            }
+        };
+        let game = &mut self.games[index];
+        if !game.questions.iter().any(|id| id == question_id) {
+            game.questions.push(question_id.into());
+        }
+
+        self.save()
+    }
+
+    /// Writes the history to a temporary file and moves it into place, so quitting partway
+    /// through saving can't leave it empty or cut short
+    fn save(&self) -> Result<(), String> {
+        let history_file = HistoryFile {
+            games: self.games.clone(),
+        };
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let file = File::create(&temp_path).map_err(|e| e.to_string())?;
+        serde_yaml::to_writer(&file, &history_file).map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        fs::rename(&temp_path, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// Today's date in UTC, as YYYY-MM-DD
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Converts a count of days since 1970-01-01 to a year, month and day in the Gregorian calendar
+/// (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(date: &str, seed: u64, questions: &[&str]) -> Game {
+        Game {
+            date: date.into(),
+            teams: vec!["Quizzly Bears".into()],
+            seed: Some(seed),
+            questions: questions.iter().map(|&id| id.into()).collect(),
+        }
+    }
+
+    fn history(games: Vec<Game>, seed: u64) -> History {
+        History {
+            path: std::env::temp_dir().join(format!("history-test-{}.yaml", seed)),
+            games,
+            teams: vec![],
+            seed: Some(seed),
+            date: "2024-03-02".into(),
+            current_game: None,
+        }
+    }
+
+    #[test]
+    fn restarting_a_game_the_same_day_carries_on_with_it() {
+        let mut history = history(vec![game("2024-03-02", 7, &["a"])], 7);
+        assert!(!history.used_in_last_games("a", 1));
+        assert!(!history.used_by_teams("a", &["quizzly bears".into()]));
+
+        history.record("b").unwrap();
+        assert_eq!(history.games.len(), 1);
+        assert_eq!(history.games[0].questions, ["a", "b"]);
+        fs::remove_file(&history.path).unwrap();
+    }
+
+    #[test]
+    fn the_same_seed_on_another_day_is_a_new_game() {
+        let mut history = history(vec![game("2024-02-24", 8, &["a"])], 8);
+        assert!(history.used_in_last_games("a", 1));
+        assert!(history.used_by_teams("a", &["Quizzly Bears".into()]));
+
+        history.record("b").unwrap();
+        assert_eq!(history.games.len(), 2);
+        assert_eq!(history.games[1].date, "2024-03-02");
+        assert_eq!(history.games[1].questions, ["b"]);
+        fs::remove_file(&history.path).unwrap();
+    }
+
+    #[test]
+    fn only_the_last_few_games_are_recent() {
+        let history = history(
+            vec![
+                game("2024-02-10", 1, &["a"]),
+                game("2024-02-17", 2, &["b"]),
+                game("2024-02-24", 3, &["c"]),
+            ],
+            9,
+        );
+        assert!(!history.used_in_last_games("a", 2));
+        assert!(history.used_in_last_games("b", 2));
+        assert!(history.used_in_last_games("a", 3));
+    }
+
+    #[test]
+    fn days_convert_to_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+
+use crate::markup::{escape_html, html};
+use crate::questions::{Clue, Question, QuestionSet, QuestionType};
+use crate::TIME_PER_QUESTION;
+
+const STYLE: &str = "
+body { font-family: sans-serif; font-size: 11pt; margin: 2em; }
+h1 { margin-bottom: 0; }
+h2 { page-break-before: always; border-bottom: 2px solid #333; }
+.question { page-break-inside: avoid; margin-bottom: 1.5em; }
+.question h3 { margin-bottom: 0.3em; }
+.team { font-weight: normal; color: #555; }
+ol { margin: 0.3em 0; }
+.answer { font-weight: bold; }
+.notes { color: #555; font-style: italic; }
+.scoring { font-size: 9pt; color: #555; }
+@page { margin: 1.5cm; }
+@media print { body { margin: 0; } }
+";
+
+/// Writes the questions out as an HTML page for the host to print, in the order they'll be
+/// played, with the team each question is offered to
+pub fn write_host_sheet<P: AsRef<Path>>(
+    path: P,
+    question_sets: &[QuestionSet],
+    seed: u64,
+    red_goes_first: bool,
+) -> std::io::Result<()> {
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str("<title>Host sheet</title>\n");
+    page.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+
+    page.push_str("<h1>Host sheet</h1>\n");
+    page.push_str(&format!(
+        "<p>Shuffled with seed {0}. Play with <code>--seed {0}</code> so the screen matches this \
+         sheet. {1} team goes first.</p>\n",
+        seed,
+        team_name(red_goes_first)
+    ));
+    page.push_str(&format!(
+        "<p class=\"scoring\">Teams have {} seconds per question. Connections score 5, 3, 2 or 1 \
+         points after 1, 2, 3 or 4 clues. Sequences score 5, 3 or 2 points after 1, 2 or 3 \
+         clues, and the fourth item is part of the answer. A question passed over is worth 1 \
+         point to the other team.</p>\n",
+        TIME_PER_QUESTION.as_secs()
+    ));
+
+    let mut offered_to_red = red_goes_first;
+    for question_set in question_sets {
+        page.push_str(&format!("<h2>{}</h2>\n", escape_html(&question_set.title)));
+        for (i, question) in question_set.questions.iter().enumerate() {
+            write_question(&mut page, i + 1, question, offered_to_red);
+            offered_to_red = !offered_to_red;
+        }
+    }
+
+    page.push_str("</body>\n</html>\n");
+    fs::write(path, page)
+}
+
+fn write_question(page: &mut String, number: usize, question: &Question, offered_to_red: bool) {
+    let (kind, clue_count, points) = match question.question_type {
+        QuestionType::Connection => ("Connection", 4, "5 / 3 / 2 / 1"),
+        QuestionType::Sequence => ("Sequence", 3, "5 / 3 / 2"),
+    };
+
+    page.push_str("<div class=\"question\">\n");
+    page.push_str(&format!(
+        "<h3>{}. {} <span class=\"team\">for {}, passes to {}</span></h3>\n",
+        number,
+        kind,
+        team_name(offered_to_red),
+        team_name(!offered_to_red)
+    ));
+
+    page.push_str("<ol>\n");
+    for clue in &question.clues[..clue_count] {
+        page.push_str(&format!("<li>{}</li>\n", clue_html(clue)));
+    }
+    page.push_str("</ol>\n");
+
+    match question.question_type {
+        QuestionType::Connection => {
+            page.push_str(&format!(
+                "<p>Connection: <span class=\"answer\">{}</span></p>\n",
+                html(&question.connection)
+            ));
+        }
+        QuestionType::Sequence => {
+            page.push_str(&format!(
+                "<p>Fourth item: <span class=\"answer\">{}</span><br>Sequence: <span \
+                 class=\"answer\">{}</span></p>\n",
+                clue_html(&question.clues[3]),
+                html(&question.connection)
+            ));
+        }
+    }
+
+    if !question.notes.is_empty() {
+        page.push_str("<ul class=\"notes\">\n");
+        for note in &question.notes {
+            page.push_str(&format!("<li>{}</li>\n", escape_html(note)));
+        }
+        page.push_str("</ul>\n");
+    }
+
+    page.push_str(&format!(
+        "<p class=\"scoring\">{} points, or 1 to {} if passed over</p>\n",
+        points,
+        team_name(!offered_to_red)
+    ));
+    page.push_str("</div>\n");
+}
+
+fn clue_html(clue: &Clue) -> String {
+    match clue {
+        Clue::TextClue(text) => html(text),
+        Clue::PictureClue(_, caption) => format!("[picture] {}", html(caption)),
+    }
+}
+
+fn team_name(red: bool) -> &'static str {
+    if red {
+        "Red"
+    } else {
+        "Blue"
+    }
+}
@@ -1,44 +1,73 @@
 #![feature(div_duration, generators, generator_trait)]
+use animation::{AnimationFrame, Animations};
+use audio::Cue;
+use buzzers::{BuzzResult, Buzzers, Team};
+use controls::Action;
+use fonts::{ScalableFont, SizedFont};
 use lazy_static::lazy_static;
 use questions::{Clue, Question, QuestionSet, QuestionType};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use regex::Regex;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::gfx::primitives::DrawRenderer;
-use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Texture;
+use sdl2::render::{BlendMode, Canvas, RenderTarget};
 use sdl2::surface::Surface;
-use sdl2::ttf::Font;
+use sdl2::video::FullscreenType;
+use serde::Deserialize;
+use snapshot::{BuzzSnapshot, GameSnapshot};
 use std::{
     fmt::Debug,
     ops::Generator,
     ops::GeneratorState,
     pin::Pin,
+    ptr,
+    sync::mpsc,
     time::{Duration, Instant},
 };
-
+use texture_cache::TextureCache;
+use theme::{ColourRole, ElementFonts, TextElement, Theme};
+
+mod animation;
+mod assemble;
+mod audio;
+mod buzzers;
+mod controls;
+mod editor;
+mod fonts;
+mod gamepads;
+mod history;
+mod host_sheet;
+mod markup;
+mod presenter;
+mod preview;
 mod questions;
+mod server;
+mod snapshot;
+mod spreadsheet;
+mod texture_cache;
+mod theme;
+mod web_export;
 // mod questions2;
 
-const BACKGROUND_GREY: Color = Color::RGB(0x66, 0x66, 0x66);
-const BACKGROUND_RED: Color = Color::RGB(0x66, 0x33, 0x33);
-const BACKGROUND_BLUE: Color = Color::RGB(0x33, 0x33, 0xcc);
-const TILE_TEXT_COLOUR: Color = Color::RGB(0x33, 0x33, 0x33);
-const TILE_BACKGROUND_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
-const PROGRESS_BAR_BACKGROUND_COLOUR: Color = Color::RGB(0x33, 0x33, 0x33);
-const PROGRESS_BAR_FOREGROUND_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
-const PROGRESS_BAR_TEXT_COLOUR: Color = Color::RGB(0xff, 0xff, 0xff);
-const RED_SCORE_TILE_COLOUR: Color = Color::RGB(0x99, 0x66, 0x66);
-const BLUE_SCORE_TILE_COLOUR: Color = Color::RGB(0x66, 0x66, 0xff);
-const SCORE_TILE_TEXT_COLOUR: Color = Color::RGB(0xff, 0xff, 0xff);
+const WINDOW_SIZE: (u32, u32) = (1280, 720);
+/// Below this the tiles are too small to read
+const MIN_WINDOW_SIZE: (u32, u32) = (640, 360);
+/// Text shrunk below this fraction of the full size to fit its tile gets a warning
+const HEAVY_SHRINK_SCALE: f32 = 0.7;
+/// How long the window has to stay the same size before the fonts are reloaded for it, so
+/// dragging its edge doesn't reload them every frame
+const FONT_RELOAD_DELAY: Duration = Duration::from_millis(200);
 
 const TIME_PER_QUESTION: Duration = Duration::from_secs(45);
 const COUNT_IN_TIME: Duration = Duration::from_secs(3);
 const COUNTDOWN_TIME: Duration = Duration::from_secs(5);
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
 enum BackgroundColour {
     Red,
     Blue,
@@ -59,9 +88,10 @@ trait QuestionPhase: Debug {
     /// whether clue 4 should be replaced with a question mark
     fn is_clue_4_question_mark(&self) -> bool;
     fn progress_bar_position(&self) -> usize;
+    fn boxed_clone(&self) -> Box<dyn QuestionPhase>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum ConnectionPhase {
     CountIn,
     OneClueShown,
@@ -185,9 +215,13 @@ impl QuestionPhase for ConnectionPhase {
     fn progress_bar_position(&self) -> usize {
         self.clues_to_show() - 1
     }
+
+    fn boxed_clone(&self) -> Box<dyn QuestionPhase> {
+        Box::new(self.clone())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum SequencePhase {
     CountIn,
     OneClueShown,
@@ -315,14 +349,83 @@ impl QuestionPhase for SequencePhase {
             SequencePhase::AnswerShown => 3,
         }
     }
+
+    fn boxed_clone(&self) -> Box<dyn QuestionPhase> {
+        Box::new(self.clone())
+    }
 }
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 struct KeyboardInput {
     next: bool,
     stop: bool,
     correct: bool,
     incorrect: bool,
+    undo: bool,
+    pause: bool,
+    red_score_adjust: i32,
+    blue_score_adjust: i32,
+    buzz: Option<(Team, Instant)>,
+    reset_buzzers: bool,
+    toggle_help: bool,
+    toggle_captions: bool,
+    toggle_mute: bool,
+    toggle_fullscreen: bool,
+    quit: bool,
+}
+
+impl KeyboardInput {
+    /// `at` is when the action happened, which matters for buzzes
+    fn apply(&mut self, action: Action, at: Instant) {
+        match action {
+            Action::Next => self.next = true,
+            Action::Stop => self.stop = true,
+            Action::Correct => self.correct = true,
+            Action::Incorrect => self.incorrect = true,
+            Action::Undo => self.undo = true,
+            Action::Pause => self.pause = true,
+            Action::RedScoreUp => self.red_score_adjust += 1,
+            Action::RedScoreDown => self.red_score_adjust -= 1,
+            Action::BlueScoreUp => self.blue_score_adjust += 1,
+            Action::BlueScoreDown => self.blue_score_adjust -= 1,
+            Action::BuzzRed => self.buzz_at(Team::Red, at),
+            Action::BuzzBlue => self.buzz_at(Team::Blue, at),
+            Action::ResetBuzzers => self.reset_buzzers = true,
+            Action::ToggleHelp => self.toggle_help = !self.toggle_help,
+            Action::ToggleCaptions => self.toggle_captions = !self.toggle_captions,
+            Action::ToggleMute => self.toggle_mute = !self.toggle_mute,
+            Action::ToggleFullscreen => self.toggle_fullscreen = !self.toggle_fullscreen,
+            Action::Quit => self.quit = true,
+        }
+    }
+
+    /// Only the earliest buzz in a frame matters
+    fn buzz_at(&mut self, team: Team, at: Instant) {
+        match self.buzz {
+            Some((_, earlier)) if earlier <= at => {}
+            _ => self.buzz = Some((team, at)),
+        }
+    }
+}
+
+/// The state of a question before an input changed it, so that the change can be undone
+#[derive(Debug)]
+struct UndoSnapshot {
+    phase: Box<dyn QuestionPhase>,
+    stopped: Option<Instant>,
+    red_points_change: i32,
+    blue_points_change: i32,
+}
+
+impl UndoSnapshot {
+    fn new(phase: &dyn QuestionPhase, stopped: Option<Instant>, result: &UpdateResult) -> Self {
+        Self {
+            phase: phase.boxed_clone(),
+            stopped,
+            red_points_change: result.red_points_change,
+            blue_points_change: result.blue_points_change,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -332,12 +435,18 @@ enum QuestionState {
         title: String,
     },
     Question {
+        question_type: QuestionType,
         clues: Vec<Clue>,
         connection: String,
+        notes: Vec<String>,
         phase: Box<dyn QuestionPhase>,
         offered_to_red: bool, // question initially given to red team
         started: Instant,
         stopped: Option<Instant>,
+        paused: Option<Instant>,
+        history: Vec<UndoSnapshot>,
+        /// Whether picture captions have been switched from how they'd normally be shown
+        captions_toggled: bool,
     },
     EndPage,
 }
@@ -347,6 +456,7 @@ struct UpdateResult {
     next_question: bool,
     red_points_change: i32,
     blue_points_change: i32,
+    cue: Option<Cue>,
 }
 
 impl UpdateResult {
@@ -355,6 +465,7 @@ impl UpdateResult {
             next_question: false,
             red_points_change: 0,
             blue_points_change: 0,
+            cue: None,
         }
     }
 
@@ -363,6 +474,7 @@ impl UpdateResult {
             next_question: true,
             red_points_change: 0,
             blue_points_change: 0,
+            cue: None,
         }
     }
 
@@ -372,18 +484,69 @@ impl UpdateResult {
                 next_question: false,
                 red_points_change: points,
                 blue_points_change: 0,
+                cue: None,
             }
         } else {
             Self {
                 next_question: false,
                 red_points_change: 0,
                 blue_points_change: points,
+                cue: None,
             }
         }
     }
+
+    pub fn with_cue(self, cue: Cue) -> Self {
+        Self {
+            cue: Some(cue),
+            ..self
+        }
+    }
 }
 
 impl QuestionState {
+    /// A question in its count-in, with the clock starting at `started`
+    pub fn new_question(question: Question, offered_to_red: bool, started: Instant) -> Self {
+        let phase: Box<dyn QuestionPhase> = match question.question_type {
+            QuestionType::Connection => Box::new(ConnectionPhase::CountIn),
+            QuestionType::Sequence => Box::new(SequencePhase::CountIn),
+        };
+
+        QuestionState::Question {
+            question_type: question.question_type,
+            clues: question.clues,
+            connection: question.connection,
+            notes: question.notes,
+            phase,
+            offered_to_red,
+            started,
+            stopped: None,
+            paused: None,
+            history: Vec::new(),
+            captions_toggled: false,
+        }
+    }
+
+    /// The question being played, taken back out of the state
+    pub fn into_question(self) -> Option<Question> {
+        match self {
+            QuestionState::Question {
+                question_type,
+                clues,
+                connection,
+                notes,
+                ..
+            } => Some(Question {
+                question_type,
+                connection,
+                clues,
+                notes,
+                ..Question::new(question_type, String::new())
+            }),
+            _ => None,
+        }
+    }
+
     /// Return value is whether to advance to the next question
     pub fn update(&mut self, input: KeyboardInput) -> UpdateResult {
         match self {
@@ -398,14 +561,50 @@ impl QuestionState {
                 phase,
                 started,
                 stopped,
+                paused,
+                history,
                 offered_to_red,
+                captions_toggled,
                 ..
             } => {
-                if phase.is_count_in() {
+                if input.toggle_captions {
+                    *captions_toggled = !*captions_toggled;
+                }
+
+                if input.pause && stopped.is_none() && !phase.is_answer_shown() {
+                    match paused.take() {
+                        // push the start time back by however long we were paused for
+                        Some(paused_at) => *started += Instant::now() - paused_at,
+                        None => *paused = Some(Instant::now()),
+                    }
+                }
+
+                if paused.is_some() {
+                    UpdateResult::no_change()
+                } else if input.undo {
+                    if let Some(snapshot) = history.pop() {
+                        if let (Some(stopped_at), None) = (*stopped, snapshot.stopped) {
+                            // don't count the time the clock was stopped for
+                            *started += Instant::now() - stopped_at;
+                        }
+                        *phase = snapshot.phase;
+                        *stopped = snapshot.stopped;
+                        UpdateResult {
+                            next_question: false,
+                            red_points_change: -snapshot.red_points_change,
+                            blue_points_change: -snapshot.blue_points_change,
+                            cue: None,
+                        }
+                    } else {
+                        UpdateResult::no_change()
+                    }
+                } else if phase.is_count_in() {
                     if *started <= Instant::now() {
                         phase.next();
+                        UpdateResult::no_change().with_cue(Cue::ClueRevealed)
+                    } else {
+                        UpdateResult::no_change()
                     }
-                    UpdateResult::no_change()
                 } else if phase.is_answer_shown() {
                     if input.next {
                         UpdateResult::next_question()
@@ -415,11 +614,16 @@ impl QuestionState {
                 } else if phase.is_passed_over() {
                     if input.correct {
                         let points = phase.get_points();
+                        let result =
+                            UpdateResult::points(points, !*offered_to_red).with_cue(Cue::Correct);
+                        history.push(UndoSnapshot::new(phase.as_ref(), *stopped, &result));
                         phase.show_answer();
-                        UpdateResult::points(points, !*offered_to_red)
+                        result
                     } else if input.incorrect {
+                        let result = UpdateResult::no_change().with_cue(Cue::Incorrect);
+                        history.push(UndoSnapshot::new(phase.as_ref(), *stopped, &result));
                         phase.show_answer();
-                        UpdateResult::no_change()
+                        result
                     } else {
                         UpdateResult::no_change()
                     }
@@ -427,26 +631,44 @@ impl QuestionState {
                     // the clock has been stopped
                     if input.correct {
                         let points = phase.get_points();
+                        let result =
+                            UpdateResult::points(points, *offered_to_red).with_cue(Cue::Correct);
+                        history.push(UndoSnapshot::new(phase.as_ref(), *stopped, &result));
                         phase.show_answer();
-                        UpdateResult::points(points, *offered_to_red)
+                        result
                     } else if input.incorrect {
+                        let result = UpdateResult::no_change().with_cue(Cue::PassedOver);
+                        history.push(UndoSnapshot::new(phase.as_ref(), *stopped, &result));
                         phase.pass_over();
-                        UpdateResult::no_change()
+                        result
                     } else {
                         UpdateResult::no_change()
                     }
                 } else {
                     // clock is still running
                     if Instant::now() - *started >= TIME_PER_QUESTION {
-                        // out of time
+                        // out of time. Undoing this leaves the clock stopped as it ran out, as if
+                        // it had been stopped just in time, rather than running out again.
+                        let result = UpdateResult::no_change().with_cue(Cue::TimeUp);
+                        let ran_out = *started + TIME_PER_QUESTION;
+                        history.push(UndoSnapshot::new(phase.as_ref(), Some(ran_out), &result));
                         phase.pass_over();
-                        UpdateResult::no_change()
+                        result
                     } else if input.next {
+                        let snapshot =
+                            UndoSnapshot::new(phase.as_ref(), *stopped, &UpdateResult::no_change());
                         phase.next();
-                        UpdateResult::no_change()
+                        if phase.clues_to_show() != snapshot.phase.clues_to_show() {
+                            history.push(snapshot);
+                            UpdateResult::no_change().with_cue(Cue::ClueRevealed)
+                        } else {
+                            UpdateResult::no_change()
+                        }
                     } else if input.stop {
+                        let result = UpdateResult::no_change().with_cue(Cue::ClockStopped);
+                        history.push(UndoSnapshot::new(phase.as_ref(), *stopped, &result));
                         *stopped = Some(Instant::now());
-                        UpdateResult::no_change()
+                        result
                     } else {
                         UpdateResult::no_change()
                     }
@@ -456,6 +678,158 @@ impl QuestionState {
         }
     }
 
+    /// Whether picture clues have their captions. They appear with the answer, unless they've
+    /// been toggled.
+    pub fn captions_shown(&self) -> bool {
+        match self {
+            QuestionState::Question {
+                phase,
+                captions_toggled,
+                ..
+            } => phase.is_answer_shown() != *captions_toggled,
+            _ => false,
+        }
+    }
+
+    /// Whether anything on screen changes by itself, without any input, so the screen has to be
+    /// redrawn every frame
+    pub fn is_animating(&self) -> bool {
+        match self {
+            QuestionState::Question {
+                phase,
+                stopped: None,
+                paused: None,
+                ..
+            } => {
+                phase.is_count_in()
+                    || (phase.is_progress_bar_shown()
+                        && !phase.is_passed_over()
+                        && !phase.is_answer_shown())
+            }
+            _ => false,
+        }
+    }
+
+    /// The cue for time passing between the last frame and this one: a tick for each second of
+    /// the count-in, and a warning when the countdown appears
+    pub fn clock_cue(&self, last_frame: Instant, now: Instant) -> Option<Cue> {
+        match self {
+            QuestionState::Question {
+                phase,
+                started,
+                stopped: None,
+                paused: None,
+                ..
+            } => {
+                if phase.is_count_in() {
+                    let whole_seconds_left =
+                        |t: Instant| started.saturating_duration_since(t).as_secs_f32().ceil();
+                    let seconds_left = whole_seconds_left(now);
+                    if seconds_left > 0.0 && seconds_left < whole_seconds_left(last_frame) {
+                        Some(Cue::CountInTick)
+                    } else {
+                        None
+                    }
+                } else if phase.is_passed_over() || phase.is_answer_shown() {
+                    None
+                } else {
+                    let time_up = *started + TIME_PER_QUESTION;
+                    if time_up.saturating_duration_since(last_frame) >= COUNTDOWN_TIME
+                        && time_up.saturating_duration_since(now) < COUNTDOWN_TIME
+                    {
+                        Some(Cue::FinalSeconds)
+                    } else {
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes the state for remote displays. Scores, the set title and buzzes are tracked
+    /// outside the question state, so are left for the caller to fill in.
+    pub fn snapshot(&self) -> GameSnapshot {
+        match self {
+            QuestionState::StartPage => GameSnapshot {
+                screen: "start",
+                ..Default::default()
+            },
+            QuestionState::TitlePage { .. } => GameSnapshot {
+                screen: "title",
+                ..Default::default()
+            },
+            QuestionState::Question {
+                question_type,
+                clues,
+                connection,
+                notes,
+                phase,
+                offered_to_red,
+                started,
+                stopped,
+                paused,
+                ..
+            } => {
+                let now = paused.unwrap_or_else(Instant::now);
+                let team_in_play = if phase.is_answer_shown() {
+                    None
+                } else if *offered_to_red != phase.is_passed_over() {
+                    Some(Team::Red)
+                } else {
+                    Some(Team::Blue)
+                };
+                let phase_name = if phase.is_count_in() {
+                    "count_in"
+                } else if phase.is_answer_shown() {
+                    "answer_shown"
+                } else if phase.is_passed_over() {
+                    "passed_over"
+                } else {
+                    "playing"
+                };
+                let (points_available, seconds_remaining) = if phase.is_progress_bar_shown() {
+                    let time_elapsed = stopped.unwrap_or(now) - *started;
+                    let time_remaining = TIME_PER_QUESTION.saturating_sub(time_elapsed);
+                    (
+                        Some(phase.get_points()),
+                        Some(time_remaining.as_secs_f32().ceil() as u64),
+                    )
+                } else {
+                    (None, None)
+                };
+
+                GameSnapshot {
+                    screen: "question",
+                    phase: Some(phase_name),
+                    team_in_play,
+                    question_type: Some(*question_type),
+                    clues: clues.iter().map(|c| markup::plain_text(c.text())).collect(),
+                    pictures: clues
+                        .iter()
+                        .map(|c| matches!(c, Clue::PictureClue(..)))
+                        .collect(),
+                    captions_shown: self.captions_shown(),
+                    clues_shown: phase.clues_to_show(),
+                    fourth_clue_hidden: phase.is_clue_4_question_mark(),
+                    connection: Some(markup::plain_text(connection)),
+                    answer_shown: phase.is_answer_shown(),
+                    passed_over: phase.is_passed_over(),
+                    clock_stopped: stopped.is_some(),
+                    paused: paused.is_some(),
+                    points_available,
+                    seconds_remaining,
+                    notes: notes.clone(),
+                    ..Default::default()
+                }
+            }
+            QuestionState::EndPage => GameSnapshot {
+                screen: "end",
+                ..Default::default()
+            },
+        }
+    }
+
     pub fn get_background_colour(&self) -> BackgroundColour {
         match self {
             QuestionState::StartPage | QuestionState::TitlePage { .. } | QuestionState::EndPage => {
@@ -486,19 +860,324 @@ impl QuestionState {
     }
 }
 
+/// Documents that can be made from the questions instead of playing them
+#[derive(Debug)]
+enum Export {
+    /// A printable sheet of the questions for the host, written to a file
+    HostSheet(String),
+    /// The game as a single web page, for playing remotely
+    Web(String),
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    /// Address to run the remote control server on, if any
+    serve: Option<String>,
+    /// Whether to open the presenter window
+    presenter: bool,
+    /// Only check the questions, rather than playing them
+    check: bool,
+    /// Whether to start in fullscreen
+    fullscreen: bool,
+    /// Question file whose screens are rendered or compared, rather than playing the game
+    screens_questions: Option<String>,
+    /// Directory to render every screen to as images
+    render: Option<String>,
+    /// Directory of previously rendered images to compare every screen against
+    compare: Option<String>,
+    /// Size to render screens at, rather than the default window size
+    size: Option<(u32, u32)>,
+    /// Theme file to use instead of theme.yaml
+    theme: Option<String>,
+    /// Seed for shuffling the questions and picking the team to go first
+    seed: Option<u64>,
+    /// Document to export, rather than playing the game
+    export: Option<Export>,
+    /// Spreadsheet of questions to convert to a question file, rather than playing the game
+    import: Option<(String, String)>,
+    /// Game spec to pick questions for and manifest to write, rather than playing the game
+    assemble: Option<(String, String)>,
+    /// Question file to open in the editor, rather than playing the game
+    edit: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--fullscreen" => options.fullscreen = true,
+            "--presenter" => options.presenter = true,
+            "--serve" => {
+                options.serve = Some(
+                    args.next()
+                        .ok_or("--serve needs an address, e.g. 0.0.0.0:8080")?,
+                )
+            }
+            "--seed" => {
+                options.seed = Some(
+                    args.next()
+                        .and_then(|seed| seed.parse().ok())
+                        .ok_or("--seed needs a number")?,
+                )
+            }
+            "export" => {
+                options.export = match args.next().as_deref() {
+                    Some("host-sheet") => Some(Export::HostSheet(
+                        args.next()
+                            .ok_or("export host-sheet needs a file to write")?,
+                    )),
+                    Some("web") => Some(Export::Web(
+                        args.next().ok_or("export web needs a file to write")?,
+                    )),
+                    _ => return Err("export needs a kind of document: host-sheet or web".into()),
+                }
+            }
+            "import" => match (args.next(), args.next()) {
+                (Some(spreadsheet), Some(output)) => options.import = Some((spreadsheet, output)),
+                _ => return Err("import needs a spreadsheet to read and a file to write".into()),
+            },
+            "assemble" => match (args.next(), args.next()) {
+                (Some(spec), Some(manifest)) => options.assemble = Some((spec, manifest)),
+                _ => {
+                    return Err("assemble needs a game spec to read and a manifest to write".into())
+                }
+            },
+            "edit" => options.edit = Some(args.next().ok_or("edit needs a question file")?),
+            "--theme" => options.theme = Some(args.next().ok_or("--theme needs a theme file")?),
+            "--render" | "--compare" => {
+                let (questions, dir) = match (args.next(), args.next()) {
+                    (Some(questions), Some(dir)) => (questions, dir),
+                    _ => return Err(format!("{} needs a question file and a directory", arg)),
+                };
+                if options
+                    .screens_questions
+                    .as_ref()
+                    .is_some_and(|q| *q != questions)
+                {
+                    return Err("--render and --compare need the same question file".into());
+                }
+                options.screens_questions = Some(questions);
+                if arg == "--render" {
+                    options.render = Some(dir);
+                } else {
+                    options.compare = Some(dir);
+                }
+            }
+            "--size" => {
+                let size = args.next().unwrap_or_default();
+                options.size = match size.split_once('x') {
+                    Some((width, height)) => match (width.parse(), height.parse()) {
+                        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                if options.size.is_none() {
+                    return Err("--size needs a size in pixels, e.g. 1920x1080".into());
+                }
+            }
+            _ => return Err(format!("Unknown argument {}", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
 pub fn main() {
+    let options = match parse_options() {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    // without this Windows scales the window up itself on HiDPI screens, which makes everything
+    // blurry. Other platforms only give a HiDPI drawable to windows that ask for one.
+    #[cfg(windows)]
     unsafe {
         winapi::um::winuser::SetProcessDPIAware();
     }
 
+    // only the game and the editor need a display, so the file tools and rendering work
+    // without one
     let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
     let ttf_context = sdl2::ttf::init().unwrap();
 
     // questions2::generate_test();
 
-    let questions = match questions::load_question_sets("./io_trivia.txt") {
-        Ok(qs) => qs,
+    if let Some((spreadsheet_path, output_path)) = options.import {
+        let question_set = match spreadsheet::load_spreadsheet(&spreadsheet_path) {
+            Ok(qs) => qs,
+            Err(es) => {
+                eprintln!("Error(s) importing {}", spreadsheet_path);
+                for e in es {
+                    eprintln!("{}", e);
+                }
+                std::process::exit(1);
+            }
+        };
+        match questions::save_questions(&output_path, &question_set) {
+            Ok(()) => println!(
+                "Imported {} questions from {} to {}",
+                question_set.questions.len(),
+                spreadsheet_path,
+                output_path
+            ),
+            Err(e) => {
+                eprintln!("Could not write {}: {}", output_path, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let font_paths = match fonts::load_font_list(&ttf_context, "./fonts.yaml") {
+        Ok(f) => f,
+        Err(es) => {
+            eprintln!("Error(s) loading fonts");
+            for e in es {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    };
+
+    // the theme next to the game is optional, but one asked for with --theme has to be there
+    let theme = match options.theme {
+        Some(ref theme_path) => theme::load_theme(theme_path, false),
+        None => theme::load_theme("./theme.yaml", true),
+    };
+    let theme = match theme {
+        Ok(t) => t,
+        Err(es) => {
+            eprintln!("Error(s) loading theme");
+            for e in es {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    };
+
+    // screens are rendered from a single question file, in order, so the same file always gives
+    // the same images
+    if let Some(ref questions_path) = options.screens_questions {
+        let question_set = match questions::load_questions(questions_path) {
+            Ok(qs) => qs,
+            Err(es) => {
+                eprintln!("Error(s) loading {}", questions_path);
+                for e in es {
+                    eprintln!("{}", e);
+                }
+                std::process::exit(1);
+            }
+        };
+        let size = options.size.unwrap_or(WINDOW_SIZE);
+        let screens = match preview::render_screens(
+            &ttf_context,
+            &font_paths,
+            &theme,
+            vec![question_set],
+            size,
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Could not render screens: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Some(ref dir) = options.render {
+            match screens.save(dir) {
+                Ok(count) => println!("Rendered {} screens to {}", count, dir),
+                Err(e) => {
+                    eprintln!("Could not save screens to {}: {}", dir, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if let Some(ref dir) = options.compare {
+            let mismatches = screens.compare(dir);
+            for mismatch in &mismatches {
+                eprintln!("{}", mismatch);
+            }
+            if !mismatches.is_empty() {
+                std::process::exit(1);
+            }
+            println!("All screens match {}", dir);
+        }
+        return;
+    }
+
+    if let Some(ref path) = options.edit {
+        let video_subsystem = sdl_context.video().unwrap();
+        if let Err(e) = editor::run_editor(
+            &sdl_context,
+            &video_subsystem,
+            &ttf_context,
+            &font_paths,
+            &theme,
+            path,
+        ) {
+            eprintln!("Error editing {}", path);
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // the seed decides everything that's random about a game, so a game can be played again or
+    // exported in the same order
+    let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut history = match history::load_history("./history.yaml") {
+        Ok(h) => h,
+        Err(es) => {
+            eprintln!("Error(s) loading history");
+            for e in es {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    };
+    history.set_seed(seed);
+
+    if let Some((spec_path, manifest_path)) = options.assemble {
+        let spec = match assemble::load_game_spec(&spec_path) {
+            Ok(s) => s,
+            Err(es) => {
+                eprintln!("Error(s) loading {}", spec_path);
+                for e in es {
+                    eprintln!("{}", e);
+                }
+                std::process::exit(1);
+            }
+        };
+        match assemble::assemble_game(&spec, &manifest_path, &history, &mut rng, seed) {
+            Ok(set_paths) => {
+                println!("Wrote {} with seed {}, using:", manifest_path, seed);
+                for set_path in set_paths {
+                    println!("{}", set_path.display());
+                }
+            }
+            Err(es) => {
+                eprintln!("Error(s) assembling game");
+                for e in es {
+                    eprintln!("{}", e);
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let questions = match questions::load_manifest("./io_trivia.txt", &history, &mut rng) {
+        Ok(manifest) => {
+            history.set_teams(manifest.teams);
+            manifest.question_sets
+        }
         Err(es) => {
             eprintln!("Error(s) loading file");
             for e in es {
@@ -507,37 +1186,104 @@ pub fn main() {
             return;
         }
     };
+    let red_goes_first: bool = rng.gen();
+
+    let controls = match controls::load_controls("./controls.yaml") {
+        Ok(c) => c,
+        Err(es) => {
+            eprintln!("Error(s) loading controls");
+            for e in es {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    };
+
+    if let Some(export) = options.export {
+        match export {
+            Export::HostSheet(path) => {
+                match host_sheet::write_host_sheet(&path, &questions, seed, red_goes_first) {
+                    Ok(()) => println!("Wrote host sheet to {} with seed {}", path, seed),
+                    Err(e) => {
+                        eprintln!("Could not write host sheet to {}: {}", path, e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Export::Web(path) => {
+                match web_export::write_web_game(&path, &questions, &controls, red_goes_first) {
+                    Ok(()) => println!("Wrote web game to {} with seed {}", path, seed),
+                    Err(es) => {
+                        eprintln!("Error(s) writing web game to {}", path);
+                        for e in es {
+                            eprintln!("{}", e);
+                        }
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        return;
+    }
+
+    // the question text doesn't depend on the game, so can be checked before it starts
+    let check_metrics = Metrics::from_window_dimensions(WINDOW_SIZE);
+    let check_fonts = match theme.load_fonts(&ttf_context, &font_paths, check_metrics.text_size) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Error loading theme fonts: {}", e);
+            return;
+        }
+    };
+    let text_warnings = check_text_fits(&questions, &check_fonts, &check_metrics);
+    for warning in &text_warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if options.check {
+        if text_warnings.is_empty() {
+            println!("No problems found");
+        }
+        return;
+    }
+
+    let video_subsystem = sdl_context.video().unwrap();
+    let mut gamepads = gamepads::Gamepads::new(&sdl_context).unwrap();
+    let timer = sdl_context.timer().unwrap();
+    let sound_config = match audio::load_sound_config("./sounds.yaml") {
+        Ok(c) => c,
+        Err(es) => {
+            eprintln!("Error(s) loading sound config");
+            for e in es {
+                eprintln!("{}", e);
+            }
+            return;
+        }
+    };
+    let mut sounds = match audio::Sounds::new(&sdl_context, &sound_config) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            eprintln!(
+                "Could not initialise audio, continuing without sound: {}",
+                e
+            );
+            None
+        }
+    };
+
+    println!("Questions shuffled with seed {}", seed);
 
     let mut question_state_generator = || {
-        let mut team_is_red: bool = rand::thread_rng().gen();
+        let mut team_is_red = red_goes_first;
 
         yield QuestionState::StartPage;
 
         for set in questions {
             yield QuestionState::TitlePage { title: set.title };
             for q in set.questions {
-                match q.question_type {
-                    QuestionType::Connection => {
-                        yield QuestionState::Question {
-                            clues: q.clues,
-                            connection: q.connection,
-                            phase: Box::new(ConnectionPhase::CountIn),
-                            offered_to_red: team_is_red,
-                            started: Instant::now() + COUNT_IN_TIME,
-                            stopped: None,
-                        }
-                    }
-                    QuestionType::Sequence => {
-                        yield QuestionState::Question {
-                            clues: q.clues,
-                            connection: q.connection,
-                            phase: Box::new(SequencePhase::CountIn),
-                            offered_to_red: team_is_red,
-                            started: Instant::now() + COUNT_IN_TIME,
-                            stopped: None,
-                        }
-                    }
+                if let Err(e) = history.record(&q.id()) {
+                    eprintln!("Could not save question history: {}", e);
                 }
+                yield QuestionState::new_question(q, team_is_red, Instant::now() + COUNT_IN_TIME);
                 team_is_red = !team_is_red;
             }
         }
@@ -554,20 +1300,24 @@ pub fn main() {
 
     let mut red_points = 0;
     let mut blue_points = 0;
+    let mut show_help = false;
+    let mut set_title = None;
+    let mut buzzers = Buzzers::new();
 
-    let window = video_subsystem
-        .window("Only Connect Trivia", 1280, 720)
+    let mut window_builder =
+        video_subsystem.window("Only Connect Trivia", WINDOW_SIZE.0, WINDOW_SIZE.1);
+    window_builder
         .position_centered()
-        .allow_highdpi()
-        // .fullscreen_desktop()
-        .build()
+        .resizable()
+        .allow_highdpi();
+    if options.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let mut window = window_builder.build().unwrap();
+    window
+        .set_minimum_size(MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1)
         .unwrap();
 
-    let metrics = Metrics::from_window_dimensions(window.size());
-
-    let font = ttf_context
-        .load_font("fonts/Roboto-Regular.ttf", metrics.text_size)
-        .unwrap();
     // let (_ddpi, _hdpi, _vdpi) = video_subsystem.display_dpi(0).unwrap();
 
     let mut canvas = window
@@ -577,337 +1327,707 @@ pub fn main() {
         .build()
         .unwrap();
 
+    // on HiDPI screens there are more pixels to draw in than the window's logical size says, so
+    // the layout is always based on the size of the canvas
+    let mut metrics = Metrics::from_window_dimensions(canvas.output_size().unwrap());
+    let mut fonts = theme
+        .load_fonts(&ttf_context, &font_paths, metrics.text_size)
+        .unwrap();
+
     let texture_creator = canvas.texture_creator();
+    let mut textures = TextureCache::new(&texture_creator);
+
+    let mut presenter = if options.presenter {
+        Some(presenter::Presenter::new(&video_subsystem, &ttf_context, &font_paths).unwrap())
+    } else {
+        None
+    };
+    let presenter_texture_creator = presenter.as_ref().map(|p| p.texture_creator());
+    let mut presenter_textures = presenter_texture_creator.as_ref().map(TextureCache::new);
+
+    // the main loop sleeps while nothing is happening, so other threads push this to wake it
+    let event_subsystem = sdl_context.event().unwrap();
+    let wake_event_type = unsafe { event_subsystem.register_event() }.unwrap();
+    let event_sender = event_subsystem.event_sender();
+    let wake_main_loop = move || {
+        let wake_event = Event::User {
+            timestamp: 0,
+            window_id: 0,
+            type_: wake_event_type,
+            code: 0,
+            data1: ptr::null_mut(),
+            data2: ptr::null_mut(),
+        };
+        if let Err(e) = event_sender.push_event(wake_event) {
+            eprintln!("Could not wake main loop: {}", e);
+        }
+    };
+
+    let (remote_actions_sender, remote_actions) = mpsc::channel();
+    let remote_server = match options.serve {
+        Some(ref address) => {
+            match server::RemoteServer::start(address, remote_actions_sender, wake_main_loop) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    eprintln!(
+                        "Could not start remote control server on {}: {}",
+                        address, e
+                    );
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    let mut animations = Animations::new(theme.animations());
+    // the last screen before moving on, while fading from it
+    let mut fade_from = None;
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut last_frame_time = Instant::now();
+    // when the window was last resized, while the fonts are still the size they were before
+    let mut resized_at: Option<Instant> = None;
+
     'running: loop {
+        // when nothing on screen moves by itself, sleep until something happens
+        let was_animating =
+            question_state.is_animating() || animations.is_animating(Instant::now());
+        let settling_since = resized_at
+            .into_iter()
+            .chain(presenter.as_ref().and_then(|p| p.resized_at()))
+            .min();
+        let first_event = if was_animating {
+            None
+        } else if let Some(resized_at) = settling_since {
+            let settles_in =
+                (resized_at + FONT_RELOAD_DELAY).saturating_duration_since(Instant::now());
+            event_pump.wait_event_timeout(settles_in.as_millis() as u32 + 1)
+        } else {
+            Some(event_pump.wait_event())
+        };
+
         let mut input = KeyboardInput::default();
-        for event in event_pump.poll_iter() {
+        let mut window_changed = false;
+        for event in first_event.into_iter().chain(event_pump.poll_iter()) {
+            // SDL timestamps events in milliseconds, which is more precise than the frame time
+            let event_time = Instant::now()
+                - Duration::from_millis(timer.ticks().saturating_sub(event.get_timestamp()) as u64);
+
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => break 'running,
-                Event::KeyDown {
-                    keycode: Some(Keycode::N),
-                    repeat: false,
-                    ..
-                } => input.next = true,
-                Event::KeyDown {
-                    keycode: Some(Keycode::S),
-                    repeat: false,
-                    ..
-                } => input.stop = true,
-                Event::KeyDown {
-                    keycode: Some(Keycode::C),
-                    repeat: false,
+                Event::Quit { .. } => break 'running,
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
                     ..
-                } => input.correct = true,
+                } => {
+                    // with the presenter window open, closing one window doesn't quit
+                    if window_id == canvas.window().id() {
+                        break 'running;
+                    }
+                    if presenter.as_ref().map(|p| p.window_id()) == Some(window_id) {
+                        presenter = None;
+                    }
+                }
+                Event::Window { .. } => window_changed = true,
                 Event::KeyDown {
-                    keycode: Some(Keycode::I),
+                    keycode: Some(keycode),
                     repeat: false,
                     ..
-                } => input.incorrect = true,
-                _ => {}
+                } => {
+                    if let Some(action) = controls.action_for_key(keycode) {
+                        input.apply(action, event_time);
+                    }
+                }
+                _ => {
+                    if let Some(action) = gamepads.handle_event(&event, &controls) {
+                        input.apply(action, event_time);
+                    }
+                }
+            }
+        }
+
+        for (action, at) in remote_actions.try_iter() {
+            input.apply(action, at);
+        }
+
+        if input.quit {
+            break 'running;
+        }
+
+        if input.toggle_help {
+            show_help = !show_help;
+        }
+
+        if input.toggle_fullscreen {
+            let window = canvas.window_mut();
+            let fullscreen = match window.fullscreen_state() {
+                FullscreenType::Off => FullscreenType::Desktop,
+                FullscreenType::True | FullscreenType::Desktop => FullscreenType::Off,
+            };
+            if let Err(e) = window.set_fullscreen(fullscreen) {
+                eprintln!("Could not change fullscreen mode: {}", e);
+            }
+        }
+
+        if input.toggle_mute {
+            if let Some(ref mut sounds) = sounds {
+                let muted = sounds.toggle_mute();
+                println!("Sound {}", if muted { "muted" } else { "unmuted" });
             }
         }
 
         let update_result = question_state.update(input);
+        let frame_time = Instant::now();
+        if let Some(ref sounds) = sounds {
+            if let Some(cue) = update_result.cue {
+                sounds.play(cue);
+            }
+            if let Some(cue) = question_state.clock_cue(last_frame_time, frame_time) {
+                sounds.play(cue);
+            }
+        }
+        last_frame_time = frame_time;
 
-        blue_points += update_result.blue_points_change;
-        red_points += update_result.red_points_change;
+        blue_points += update_result.blue_points_change + input.blue_score_adjust;
+        red_points += update_result.red_points_change + input.red_score_adjust;
 
         if update_result.next_question {
+            if theme.animations().screen_fade() {
+                // keep the outgoing screen as it is now, to fade from
+                let animation = animations.frame(frame_time);
+                fade_from = texture_creator
+                    .create_texture_target(None, metrics.width, metrics.height)
+                    .ok();
+                if let Some(ref mut screen) = fade_from {
+                    screen.set_blend_mode(BlendMode::Blend);
+                    let result = canvas.with_texture_canvas(screen, |screen_canvas| {
+                        draw_screen(
+                            screen_canvas,
+                            &mut textures,
+                            &theme,
+                            &fonts,
+                            &metrics,
+                            &question_state,
+                            (red_points, blue_points),
+                            &animation,
+                        )
+                    });
+                    if let Err(e) = result {
+                        eprintln!("Could not keep the screen to fade from: {}", e);
+                        fade_from = None;
+                    }
+                }
+                animations.screen_changed(frame_time);
+            }
             match Pin::new(&mut question_state_generator).resume(()) {
                 GeneratorState::Yielded(x) => question_state = x,
                 GeneratorState::Complete(_) => break 'running,
             }
+            if let QuestionState::TitlePage { ref title } = question_state {
+                set_title = Some(title.clone());
+            }
+            buzzers.reset();
+            textures.clear();
+            if let Some(ref mut presenter_textures) = presenter_textures {
+                presenter_textures.clear();
+            }
+        }
+
+        if input.reset_buzzers {
+            buzzers.reset();
+        }
+
+        if let Some((team, at)) = input.buzz {
+            match buzzers.buzz(team, at) {
+                Some(BuzzResult::Won(buzz)) => {
+                    println!(
+                        "{:?} buzzed after {:.3} s",
+                        buzz.team,
+                        buzz.elapsed.as_secs_f64()
+                    );
+                    if let Some(ref sounds) = sounds {
+                        sounds.play(Cue::Buzzer);
+                    }
+                }
+                // the buzzer has already sounded for this buzz, just for the wrong team
+                Some(BuzzResult::Overturned(buzz)) => println!(
+                    "{:?} buzzed after {:.3} s, before {:?} whose buzz arrived first",
+                    buzz.team,
+                    buzz.elapsed.as_secs_f64(),
+                    buzz.overturned.unwrap()
+                ),
+                None => {}
+            }
+        }
+
+        animations.observe(&question_state, (red_points, blue_points), frame_time);
+
+        let needs_redraw = was_animating
+            || question_state.is_animating()
+            || animations.is_animating(frame_time)
+            || input != KeyboardInput::default()
+            || window_changed
+            || settling_since.is_some_and(|at| frame_time >= at + FONT_RELOAD_DELAY);
+        if !needs_redraw {
+            continue;
+        }
+
+        if remote_server.is_some() || presenter.is_some() {
+            let mut snapshot = question_state.snapshot();
+            snapshot.set_title = set_title.clone();
+            snapshot.red_score = red_points;
+            snapshot.blue_score = blue_points;
+            snapshot.buzz = buzzers.winner().map(|buzz| BuzzSnapshot {
+                team: buzz.team,
+                seconds: buzz.elapsed.as_secs_f64(),
+                overturned: buzz.overturned,
+            });
+
+            if let Some(ref remote_server) = remote_server {
+                remote_server.publish(&snapshot);
+            }
+            if let (Some(p), Some(presenter_textures)) =
+                (presenter.as_mut(), presenter_textures.as_mut())
+            {
+                if let Err(e) = p.draw(&snapshot, &controls, presenter_textures) {
+                    eprintln!(
+                        "Closing the presenter window, as it couldn't be drawn: {}",
+                        e
+                    );
+                    presenter = None;
+                }
+            }
+        }
+
+        let output_size = canvas.output_size().unwrap();
+        if output_size != (metrics.width, metrics.height) {
+            // text is shrunk to fit, so the old fonts do until the size settles
+            metrics = Metrics::from_window_dimensions(output_size);
+            textures.clear();
+            resized_at = Some(Instant::now());
+        }
+        if resized_at.is_some_and(|at| at.elapsed() >= FONT_RELOAD_DELAY) {
+            fonts = theme
+                .load_fonts(&ttf_context, &font_paths, metrics.text_size)
+                .unwrap();
+            textures.clear();
+            resized_at = None;
         }
 
         // drawing code here
-        match question_state.get_background_colour() {
-            BackgroundColour::Red => canvas.set_draw_color(BACKGROUND_RED),
-            BackgroundColour::Blue => canvas.set_draw_color(BACKGROUND_BLUE),
-            BackgroundColour::Grey => canvas.set_draw_color(BACKGROUND_GREY),
+        let animation = animations.frame(Instant::now());
+        draw_screen(
+            &mut canvas,
+            &mut textures,
+            &theme,
+            &fonts,
+            &metrics,
+            &question_state,
+            (red_points, blue_points),
+            &animation,
+        );
+
+        match (animation.screen_fade, &mut fade_from) {
+            (Some(opacity), Some(previous_screen)) => {
+                previous_screen.set_alpha_mod((opacity * 255.0).round() as u8);
+                canvas.copy(previous_screen, None, None).unwrap();
+            }
+            (None, Some(_)) => fade_from = None,
+            _ => {}
+        }
+
+        if let Some(buzz) = buzzers.winner() {
+            let (colour, team_name) = match buzz.team {
+                Team::Red => (theme.colour(ColourRole::RedScoreTile), "Red"),
+                Team::Blue => (theme.colour(ColourRole::BlueScoreTile), "Blue"),
+            };
+            let mut banner_text = format!("{} ({:.3} s)", team_name, buzz.elapsed.as_secs_f64());
+            if let Some(overturned) = buzz.overturned {
+                banner_text += &format!(", corrected from {:?}", overturned);
+            }
+
+            canvas.set_draw_color(colour);
+            canvas.fill_rect(metrics.buzz_banner_rect).unwrap();
+            let text_texture = textures
+                .text(
+                    &banner_text,
+                    fonts.get(TextElement::Banner),
+                    metrics.buzz_banner_rect.width(),
+                    metrics.buzz_banner_rect.height(),
+                    metrics.padding,
+                    theme.colour(ColourRole::ScoreTileText),
+                )
+                .unwrap();
+
+            canvas
+                .copy(text_texture, None, metrics.buzz_banner_rect)
+                .unwrap();
         }
 
-        canvas.clear();
+        if show_help {
+            canvas.set_blend_mode(BlendMode::Blend);
+            canvas.set_draw_color(theme.colour(ColourRole::HelpOverlay));
+            canvas.fill_rect(None).unwrap();
+            canvas.set_blend_mode(BlendMode::None);
+
+            let help_lines = controls.help_lines();
+            let help_fonts = fonts.get(TextElement::Banner);
+            // shrink the lines if there are too many to fit at full size, leaving a line's
+            // space above and below; the text is fitted to whatever height they get
+            let line_height = (help_fonts.full_size().recommended_line_spacing() as u32)
+                .min(metrics.height / (help_lines.len() as u32 + 2))
+                .max(1);
+            let y_start =
+                (metrics.height as i32 - (help_lines.len() as u32 * line_height) as i32) / 2;
+
+            for (i, line) in help_lines.iter().enumerate() {
+                let line_rect = Rect::new(
+                    0,
+                    y_start + (i as u32 * line_height) as i32,
+                    metrics.width,
+                    line_height,
+                );
+                let text_texture = textures
+                    .text(
+                        line,
+                        help_fonts,
+                        line_rect.width(),
+                        line_rect.height(),
+                        metrics.padding,
+                        theme.colour(ColourRole::HelpText),
+                    )
+                    .unwrap();
 
-        // let rerender_tiles = game_state.update(input);
+                canvas.copy(text_texture, None, line_rect).unwrap();
+            }
+        }
 
-        // // render each of the clue tiles ahead of time. Hopefully won't cause too much jank...
-        // if rerender_tiles {
-        match question_state {
-            QuestionState::StartPage => {
-                let banner_surface = render_text(
+        canvas.present();
+    }
+}
+
+/// Draws a frame of the game itself, without any overlays. The same drawing is used for the
+/// window and for rendering previews offscreen.
+#[allow(clippy::too_many_arguments)]
+fn draw_screen<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    textures: &mut TextureCache<'_, T::Context>,
+    theme: &Theme,
+    fonts: &ElementFonts,
+    metrics: &Metrics,
+    question_state: &QuestionState,
+    (red_points, blue_points): (i32, i32),
+    animation: &AnimationFrame,
+) {
+    let background = question_state.get_background_colour();
+    match background {
+        BackgroundColour::Red => canvas.set_draw_color(theme.colour(ColourRole::BackgroundRed)),
+        BackgroundColour::Blue => canvas.set_draw_color(theme.colour(ColourRole::BackgroundBlue)),
+        BackgroundColour::Grey => canvas.set_draw_color(theme.colour(ColourRole::BackgroundGrey)),
+    }
+
+    canvas.clear();
+
+    if let Some(image) = theme.background_image(background) {
+        let texture = textures.background(background, image).unwrap();
+        let src_rect = metrics.get_background_src_rect(image.size());
+        canvas.copy(texture, src_rect, None).unwrap();
+    }
+
+    // let rerender_tiles = game_state.update(input);
+
+    // // render each of the clue tiles ahead of time. Hopefully won't cause too much jank...
+    // if rerender_tiles {
+    match question_state {
+        QuestionState::StartPage => {
+            let banner_texture = textures
+                .text(
                     "Only Connect",
-                    &font,
+                    fonts.get(TextElement::Banner),
                     metrics.width,
                     metrics.height,
                     metrics.padding,
-                    TILE_TEXT_COLOUR,
+                    theme.colour(ColourRole::BannerText),
                 )
                 .unwrap();
 
-                let banner_texture = texture_creator
-                    .create_texture_from_surface(banner_surface)
-                    .unwrap();
-
-                canvas.copy(&banner_texture, None, None).unwrap();
-            }
-            QuestionState::TitlePage { ref title } => {
-                let banner_surface = render_text(
+            canvas.copy(banner_texture, None, None).unwrap();
+        }
+        QuestionState::TitlePage { ref title } => {
+            let banner_texture = textures
+                .text(
                     title,
-                    &font,
+                    fonts.get(TextElement::Banner),
                     metrics.width,
                     metrics.height,
                     metrics.padding,
-                    TILE_TEXT_COLOUR,
+                    theme.colour(ColourRole::BannerText),
                 )
                 .unwrap();
 
-                let banner_texture = texture_creator
-                    .create_texture_from_surface(banner_surface)
-                    .unwrap();
-
-                canvas.copy(&banner_texture, None, None).unwrap();
-            }
-            QuestionState::Question {
-                ref clues,
-                ref phase,
-                ref connection,
-                ref started,
-                ref stopped,
-                ..
-            } => {
-                for i in 0..phase.clues_to_show() {
-                    let dst_rect = metrics.get_tile_dest_rect(i);
-                    canvas.set_draw_color(TILE_BACKGROUND_COLOUR);
-                    canvas.fill_rect(dst_rect).unwrap();
-
-                    let override_question_mark = (i == 3) && phase.is_clue_4_question_mark();
-
-                    match (override_question_mark, &clues[i]) {
-                        (true, _) => {
-                            let text_surface = render_text(
+            canvas.copy(banner_texture, None, None).unwrap();
+        }
+        QuestionState::Question {
+            ref clues,
+            ref phase,
+            ref connection,
+            ref started,
+            ref stopped,
+            ref paused,
+            ..
+        } => {
+            // while paused, everything is drawn as it was when the pause started
+            let now = paused.unwrap_or_else(Instant::now);
+
+            for (i, clue) in clues.iter().enumerate().take(phase.clues_to_show()) {
+                let dst_rect =
+                    animation.tile_rect(i, metrics.get_tile_dest_rect(i), metrics.height);
+                canvas.set_draw_color(theme.colour(ColourRole::TileBackground));
+                canvas.fill_rect(dst_rect).unwrap();
+
+                let override_question_mark = (i == 3) && phase.is_clue_4_question_mark();
+
+                match (override_question_mark, clue) {
+                    (true, _) => {
+                        let clue_texture = textures
+                            .text(
                                 "?",
-                                &font,
+                                fonts.get(TextElement::Tiles),
                                 metrics.tile_size.0,
                                 metrics.tile_size.1,
                                 metrics.padding,
-                                TILE_TEXT_COLOUR,
+                                theme.colour(ColourRole::TileText),
                             )
                             .unwrap();
 
-                            let clue_texture = texture_creator
-                                .create_texture_from_surface(text_surface)
-                                .unwrap();
-
-                            canvas.copy(&clue_texture, None, dst_rect).unwrap();
-                        }
-                        (false, Clue::TextClue(clue)) => {
-                            let text_surface = render_text(
-                                &clue,
-                                &font,
+                        canvas.copy(clue_texture, None, dst_rect).unwrap();
+                    }
+                    (false, Clue::TextClue(clue)) => {
+                        let clue_texture = textures
+                            .text(
+                                clue,
+                                fonts.get(TextElement::Tiles),
                                 metrics.tile_size.0,
                                 metrics.tile_size.1,
                                 metrics.padding,
-                                TILE_TEXT_COLOUR,
+                                theme.colour(ColourRole::TileText),
                             )
                             .unwrap();
 
-                            let clue_texture = texture_creator
-                                .create_texture_from_surface(text_surface)
+                        canvas.copy(clue_texture, None, dst_rect).unwrap();
+                    }
+                    (false, Clue::PictureClue(picture, caption)) => {
+                        let clue_texture = textures.picture(i, &picture.image).unwrap();
+
+                        let scaled_dst_rect =
+                            metrics.get_scaled_tile_dest_rect(dst_rect, picture.image.size());
+                        canvas.copy(clue_texture, None, scaled_dst_rect).unwrap();
+
+                        if question_state.captions_shown() && !caption.is_empty() {
+                            let caption_rect = Rect::new(
+                                dst_rect.x(),
+                                dst_rect.bottom() - metrics.caption_height as i32,
+                                dst_rect.width(),
+                                metrics.caption_height,
+                            );
+                            canvas.set_blend_mode(BlendMode::Blend);
+                            canvas.set_draw_color(theme.colour(ColourRole::CaptionBackground));
+                            canvas.fill_rect(caption_rect).unwrap();
+                            canvas.set_blend_mode(BlendMode::None);
+
+                            let caption_texture = textures
+                                .text(
+                                    caption,
+                                    fonts.get(TextElement::Tiles),
+                                    metrics.tile_size.0,
+                                    metrics.caption_height,
+                                    metrics.padding,
+                                    theme.colour(ColourRole::CaptionText),
+                                )
                                 .unwrap();
-
-                            canvas.copy(&clue_texture, None, dst_rect).unwrap();
+                            canvas.copy(caption_texture, None, caption_rect).unwrap();
                         }
-                        (false, Clue::PictureClue(image, _clue)) => {
-                            let clue_texture = texture_creator
-                            .create_texture_from_surface(image)
-                            .unwrap();
-
-                            let scaled_dst_rect = metrics.get_scaled_tile_dest_rect(i, image.size());
-                            canvas.copy(&clue_texture, None, scaled_dst_rect).unwrap();
-                        }
-                    };
-
-                }
-                if phase.is_answer_shown() {
-                    let dst_rect = metrics.get_answer_dest_rect();
-                    canvas.set_draw_color(TILE_BACKGROUND_COLOUR);
-                    canvas.fill_rect(dst_rect).unwrap();
-                    let text_surface = render_text(
-                        &connection,
-                        &font,
+                    }
+                };
+            }
+            if phase.is_answer_shown() {
+                let dst_rect = metrics.get_answer_dest_rect();
+                let revealed_rect = animation.answer_rect(dst_rect);
+                canvas.set_draw_color(theme.colour(ColourRole::AnswerBackground));
+                canvas.fill_rect(revealed_rect).unwrap();
+                let text_texture = textures
+                    .text(
+                        connection,
+                        fonts.get(TextElement::Answer),
                         metrics.answer_size.0,
                         metrics.answer_size.1,
                         metrics.padding,
-                        TILE_TEXT_COLOUR,
+                        theme.colour(ColourRole::AnswerText),
                     )
                     .unwrap();
 
-                    let text_texture = texture_creator
-                        .create_texture_from_surface(text_surface)
-                        .unwrap();
-
-                    canvas.copy(&text_texture, None, dst_rect).unwrap();
-                }
-                if phase.is_progress_bar_shown() {
-                    let stop_time = stopped.unwrap_or(Instant::now());
-                    let time_elapsed = stop_time - *started;
-                    let fraction_time_elapsed = time_elapsed.div_duration_f32(TIME_PER_QUESTION);
-                    let progress_bar_fraction = fraction_time_elapsed.clamp(0.0, 1.0);
-
-                    let background_dst_rect =
-                        metrics.get_progress_bar_dest_rect(phase.progress_bar_position());
-                    let fill_dst_rect = metrics.get_progress_bar_fill_dest_rect(
-                        phase.progress_bar_position(),
-                        progress_bar_fraction,
-                    );
-
-                    // draw bar background
-                    canvas.set_draw_color(PROGRESS_BAR_BACKGROUND_COLOUR);
-                    canvas.fill_rect(background_dst_rect).unwrap();
-                    // draw bar fill
-                    canvas.set_draw_color(PROGRESS_BAR_FOREGROUND_COLOUR);
-                    canvas.fill_rect(fill_dst_rect).unwrap();
-
-                    // draw points text overlay
-                    let question_points = phase.get_points();
-                    let overlay_text = match question_points {
-                        1 => "1 point".into(),
-                        _ => format!("{} points", question_points),
-                    };
-
-                    let text_surface = render_text(
+                // the text stays still while the bar grows over it
+                canvas.set_clip_rect(revealed_rect);
+                canvas.copy(text_texture, None, dst_rect).unwrap();
+                canvas.set_clip_rect(None);
+            }
+            if phase.is_progress_bar_shown() {
+                let stop_time = stopped.unwrap_or(now);
+                let time_elapsed = stop_time - *started;
+                let fraction_time_elapsed = time_elapsed.div_duration_f32(TIME_PER_QUESTION);
+                let progress_bar_fraction = fraction_time_elapsed.clamp(0.0, 1.0);
+
+                let background_dst_rect =
+                    metrics.get_progress_bar_dest_rect(phase.progress_bar_position());
+                let fill_dst_rect = metrics.get_progress_bar_fill_dest_rect(
+                    phase.progress_bar_position(),
+                    progress_bar_fraction,
+                );
+
+                // draw bar background
+                canvas.set_draw_color(theme.colour(ColourRole::ProgressBarBackground));
+                canvas.fill_rect(background_dst_rect).unwrap();
+                // draw bar fill
+                canvas.set_draw_color(theme.colour(ColourRole::ProgressBarForeground));
+                canvas.fill_rect(fill_dst_rect).unwrap();
+
+                // draw points text overlay
+                let question_points = phase.get_points();
+                let overlay_text = match question_points {
+                    1 => "1 point".into(),
+                    _ => format!("{} points", question_points),
+                };
+
+                let text_texture = textures
+                    .text(
                         &overlay_text,
-                        &font,
+                        fonts.get(TextElement::Score),
                         metrics.tile_size.0,
                         metrics.progress_bar_height,
                         metrics.padding,
-                        PROGRESS_BAR_TEXT_COLOUR,
+                        theme.colour(ColourRole::ProgressBarText),
                     )
                     .unwrap();
 
-                    let text_texture = texture_creator
-                        .create_texture_from_surface(text_surface)
-                        .unwrap();
+                canvas
+                    .copy(text_texture, None, background_dst_rect)
+                    .unwrap();
+            }
+            if paused.is_some() {
+                let pause_banner_rect = Rect::new(
+                    0,
+                    metrics.countdown_tile_rect.y(),
+                    metrics.width,
+                    metrics.countdown_tile_rect.height(),
+                );
+                let text_texture = textures
+                    .text(
+                        "Paused",
+                        fonts.get(TextElement::Banner),
+                        pause_banner_rect.width(),
+                        pause_banner_rect.height(),
+                        metrics.padding,
+                        theme.colour(ColourRole::ProgressBarText),
+                    )
+                    .unwrap();
 
-                    canvas
-                        .copy(&text_texture, None, background_dst_rect)
-                        .unwrap();
-                }
-                if phase.is_count_in() {
-                    if let Some(count_in_time) = started.checked_duration_since(Instant::now()) {
-                        let count_in_text = (count_in_time.as_secs_f32().ceil() as u32).to_string();
-                        let text_surface = render_text(
+                canvas.copy(text_texture, None, pause_banner_rect).unwrap();
+            } else if phase.is_count_in() {
+                if let Some(count_in_time) = started.checked_duration_since(now) {
+                    let count_in_text = (count_in_time.as_secs_f32().ceil() as u32).to_string();
+                    let text_texture = textures
+                        .text(
                             &count_in_text,
-                            &font,
+                            fonts.get(TextElement::Score),
                             metrics.countdown_tile_rect.width(),
                             metrics.countdown_tile_rect.height(),
                             metrics.padding,
-                            PROGRESS_BAR_TEXT_COLOUR,
+                            theme.colour(ColourRole::ProgressBarText),
                         )
                         .unwrap();
 
-                        let text_texture = texture_creator
-                            .create_texture_from_surface(text_surface)
-                            .unwrap();
-
-                        canvas
-                            .copy(&text_texture, None, metrics.countdown_tile_rect)
-                            .unwrap();
-                    }
-                } else if let None = stopped {
-                    if let Some(remaining_time) =
-                        (*started + TIME_PER_QUESTION).checked_duration_since(Instant::now())
-                    {
-                        if remaining_time < COUNTDOWN_TIME {
-                            let countdown_text =
-                                (remaining_time.as_secs_f32().ceil() as u32).to_string();
-                            let text_surface = render_text(
+                    canvas
+                        .copy(text_texture, None, metrics.countdown_tile_rect)
+                        .unwrap();
+                }
+            } else if stopped.is_none() {
+                if let Some(remaining_time) =
+                    (*started + TIME_PER_QUESTION).checked_duration_since(now)
+                {
+                    if remaining_time < COUNTDOWN_TIME {
+                        let countdown_text =
+                            (remaining_time.as_secs_f32().ceil() as u32).to_string();
+                        let text_texture = textures
+                            .text(
                                 &countdown_text,
-                                &font,
+                                fonts.get(TextElement::Score),
                                 metrics.countdown_tile_rect.width(),
                                 metrics.countdown_tile_rect.height(),
                                 metrics.padding,
-                                PROGRESS_BAR_TEXT_COLOUR,
+                                theme.colour(ColourRole::ProgressBarText),
                             )
                             .unwrap();
 
-                            let text_texture = texture_creator
-                                .create_texture_from_surface(text_surface)
-                                .unwrap();
-
-                            canvas
-                                .copy(&text_texture, None, metrics.countdown_tile_rect)
-                                .unwrap();
-                        }
+                        canvas
+                            .copy(text_texture, None, metrics.countdown_tile_rect)
+                            .unwrap();
                     }
                 }
             }
-            QuestionState::EndPage => {
-                let banner_surface = render_text(
+        }
+        QuestionState::EndPage => {
+            let banner_texture = textures
+                .text(
                     "Game over",
-                    &font,
+                    fonts.get(TextElement::Banner),
                     metrics.width,
                     metrics.height,
                     metrics.padding,
-                    TILE_TEXT_COLOUR,
+                    theme.colour(ColourRole::BannerText),
                 )
                 .unwrap();
 
-                let banner_texture = texture_creator
-                    .create_texture_from_surface(banner_surface)
-                    .unwrap();
-
-                canvas.copy(&banner_texture, None, None).unwrap();
-            }
+            canvas.copy(banner_texture, None, None).unwrap();
         }
+    }
 
-        // render scores on all pages except the start page
-        match question_state {
-            QuestionState::StartPage => {}
-            _ => {
-                let score_tiles = [
-                    (
-                        metrics.left_score_tile_rect,
-                        RED_SCORE_TILE_COLOUR,
-                        red_points,
-                    ),
-                    (
-                        metrics.right_score_tile_rect,
-                        BLUE_SCORE_TILE_COLOUR,
-                        blue_points,
-                    ),
-                ];
-
-                for (rect, colour, points) in score_tiles {
-                    canvas.set_draw_color(colour);
-                    canvas.fill_rect(rect).unwrap();
-                    let points_string = points.to_string();
-                    let text_surface = render_text(
+    // render scores on all pages except the start page
+    match question_state {
+        QuestionState::StartPage => {}
+        _ => {
+            let score_tiles = [
+                (
+                    metrics.left_score_tile_rect,
+                    theme.colour(ColourRole::RedScoreTile),
+                    red_points,
+                ),
+                (
+                    metrics.right_score_tile_rect,
+                    theme.colour(ColourRole::BlueScoreTile),
+                    blue_points,
+                ),
+            ];
+
+            for (i, &(rect, colour, points)) in score_tiles.iter().enumerate() {
+                let pulsed_rect = animation.score_rect(i, rect);
+                canvas.set_draw_color(colour);
+                canvas.fill_rect(pulsed_rect).unwrap();
+                let points_string = points.to_string();
+                let text_texture = textures
+                    .text(
                         &points_string,
-                        &font,
+                        fonts.get(TextElement::Score),
                         rect.width(),
                         rect.height(),
                         metrics.padding,
-                        SCORE_TILE_TEXT_COLOUR,
+                        theme.colour(ColourRole::ScoreTileText),
                     )
                     .unwrap();
 
-                    let text_texture = texture_creator
-                        .create_texture_from_surface(text_surface)
-                        .unwrap();
-
-                    canvas.copy(&text_texture, None, rect).unwrap();
-                }
+                canvas.copy(text_texture, None, pulsed_rect).unwrap();
             }
         }
-
-        canvas.present();
     }
 }
 
@@ -923,9 +2043,11 @@ struct Metrics {
     answer_pos: (u32, u32),
     progress_bar_y: i32,
     progress_bar_height: u32,
+    caption_height: u32,
     left_score_tile_rect: Rect,
     right_score_tile_rect: Rect,
     countdown_tile_rect: Rect,
+    buzz_banner_rect: Rect,
     text_size: u16,
 }
 
@@ -933,17 +2055,22 @@ impl Metrics {
     fn from_window_dimensions(window_dimensions: (u32, u32)) -> Self {
         let (width, height) = window_dimensions;
 
-        // width = 2 * tile_0_pos.x + 3 * intertile_spacing + 4 * tile_width
+        // the layout is designed for 16:9 and runs out of height in anything wider, so it's
+        // centred horizontally instead
+        let layout_width = width.min(height * 16 / 9);
+        let x_offset = (width - layout_width) / 2;
+
+        // layout_width = 2 * margin + 3 * intertile_spacing + 4 * tile_width
         // constrained by all of them being integers
         // tile width is 4 parts, intertile spacing is 1 part, margin is 1 part
         // these formulas give pretty reasonable results
 
-        let tile_width = width * 8 / 37;
-        let rem = width - 4 * tile_width;
+        let tile_width = layout_width * 8 / 37;
+        let rem = layout_width - 4 * tile_width;
         let tile_spacing = rem / 5 + (rem - rem / 5) % 2;
         let margin = (rem - tile_spacing * 3) / 2;
 
-        assert_eq!(tile_width * 4 + tile_spacing * 3 + margin * 2, width);
+        assert_eq!(tile_width * 4 + tile_spacing * 3 + margin * 2, layout_width);
 
         let tile_height = tile_width * 3 / 4; // might need to compensate for aspect ratio here
         let answer_width = 4 * tile_width + 3 * tile_spacing;
@@ -956,6 +2083,8 @@ impl Metrics {
         let padding = tile_height / 6;
 
         let progress_bar_height = tile_height / 4;
+        // room for a line of text at full size
+        let caption_height = tile_height / 4;
         let progress_bar_y = (tile_ypos - progress_bar_height - tile_spacing) as i32;
 
         let score_tile_width = tile_width * 2 / 3;
@@ -968,13 +2097,13 @@ impl Metrics {
         let countdown_tile_ypos = (2 * padding) as i32;
 
         let left_score_tile_rect = Rect::new(
-            margin as i32,
+            (x_offset + margin) as i32,
             margin as i32,
             score_tile_width,
             score_tile_height,
         );
         let right_score_tile_rect = Rect::new(
-            (width - margin - score_tile_width) as i32,
+            (x_offset + layout_width - margin - score_tile_width) as i32,
             margin as i32,
             score_tile_width,
             score_tile_height,
@@ -987,20 +2116,30 @@ impl Metrics {
             countdown_tile_height,
         );
 
+        // the buzz banner sits between the score tiles
+        let buzz_banner_rect = Rect::new(
+            (x_offset + margin + score_tile_width + tile_spacing) as i32,
+            margin as i32,
+            layout_width - 2 * (margin + score_tile_width + tile_spacing),
+            score_tile_height,
+        );
+
         Metrics {
             width,
             height,
             tile_size: (tile_width, tile_height),
-            tile_0_pos: (margin, tile_ypos),
+            tile_0_pos: (x_offset + margin, tile_ypos),
             tile_x_stride: tile_width + tile_spacing,
             answer_size: (answer_width, answer_height),
-            answer_pos: (margin, answer_ypos),
+            answer_pos: (x_offset + margin, answer_ypos),
             padding,
             progress_bar_y,
             progress_bar_height,
+            caption_height,
             left_score_tile_rect,
             right_score_tile_rect,
             countdown_tile_rect,
+            buzz_banner_rect,
             text_size,
         }
     }
@@ -1013,7 +2152,9 @@ impl Metrics {
         Rect::new(x, y, width, height)
     }
 
-    fn get_scaled_tile_dest_rect(&self, index: usize, image_dimensions: (u32, u32)) -> Rect {
+    /// Where a picture goes on a tile drawn at `tile_rect`, as big as it fits on a full size tile
+    /// and squashed or moved along with the tile while it's being revealed
+    fn get_scaled_tile_dest_rect(&self, tile_rect: Rect, image_dimensions: (u32, u32)) -> Rect {
         // compute scaled size maintaining aspect ratio
         let pillarboxed_width = (image_dimensions.0 as f32 * self.tile_size.1 as f32
             / image_dimensions.1 as f32)
@@ -1030,12 +2171,33 @@ impl Metrics {
             (pillarboxed_width, self.tile_size.1)
         };
 
-        let x = (self.tile_0_pos.0
-            + self.tile_x_stride * index as u32
-            + (self.tile_size.0 - scaled_dimensions.0) / 2) as i32;
-        let y = (self.tile_0_pos.1 + (self.tile_size.1 - scaled_dimensions.1) / 2) as i32;
+        let x_scale = tile_rect.width() as f32 / self.tile_size.0 as f32;
+        let y_scale = tile_rect.height() as f32 / self.tile_size.1 as f32;
+        let x_offset = (self.tile_size.0 - scaled_dimensions.0) / 2;
+        let y_offset = (self.tile_size.1 - scaled_dimensions.1) / 2;
 
-        Rect::new(x, y, scaled_dimensions.0, scaled_dimensions.1)
+        Rect::new(
+            tile_rect.x() + (x_offset as f32 * x_scale).round() as i32,
+            tile_rect.y() + (y_offset as f32 * y_scale).round() as i32,
+            (scaled_dimensions.0 as f32 * x_scale).round() as u32,
+            (scaled_dimensions.1 as f32 * y_scale).round() as u32,
+        )
+    }
+
+    /// The part of a background image to stretch over the whole window: as much of the image as
+    /// has the window's aspect ratio, from the middle
+    fn get_background_src_rect(&self, image_dimensions: (u32, u32)) -> Rect {
+        let cropped_width = ((image_dimensions.1 as u64 * self.width as u64) / self.height as u64)
+            .min(image_dimensions.0 as u64) as u32;
+        let cropped_height = ((image_dimensions.0 as u64 * self.height as u64) / self.width as u64)
+            .min(image_dimensions.1 as u64) as u32;
+
+        Rect::new(
+            ((image_dimensions.0 - cropped_width) / 2) as i32,
+            ((image_dimensions.1 - cropped_height) / 2) as i32,
+            cropped_width.max(1),
+            cropped_height.max(1),
+        )
     }
 
     fn get_answer_dest_rect(&self) -> Rect {
@@ -1063,34 +2225,99 @@ impl Metrics {
     }
 }
 
-// Renders a block of text centred and word-wrapped into a rectangle
+/// Warnings for clues and connections that only fit on their tiles by shrinking them a lot or by
+/// breaking words across lines
+fn check_text_fits(
+    question_sets: &[QuestionSet],
+    fonts: &ElementFonts,
+    metrics: &Metrics,
+) -> Vec<String> {
+    let mut warnings = vec![];
+
+    let mut check = |set_title: &str, text: &str, element, (width, height): (u32, u32)| {
+        let fit =
+            match fonts
+                .get(element)
+                .fit(&markup::parse(text), width - 2 * metrics.padding, height)
+            {
+                Ok(fit) => fit,
+                Err(e) => {
+                    warnings.push(format!(
+                        "{}: could not load a font to fit \"{}\": {}",
+                        set_title, text, e
+                    ));
+                    return;
+                }
+            };
+        if fit.words_broken {
+            warnings.push(format!(
+                "{}: \"{}\" is too long to fit without breaking words",
+                set_title, text
+            ));
+        } else if fit.scale < HEAVY_SHRINK_SCALE {
+            warnings.push(format!(
+                "{}: \"{}\" has to be shrunk to {:.0}% to fit",
+                set_title,
+                text,
+                fit.scale * 100.0
+            ));
+        }
+    };
+
+    for question_set in question_sets {
+        for question in &question_set.questions {
+            for clue in &question.clues {
+                if let Clue::TextClue(text) = clue {
+                    check(
+                        &question_set.title,
+                        text,
+                        TextElement::Tiles,
+                        metrics.tile_size,
+                    );
+                }
+            }
+            check(
+                &question_set.title,
+                &question.connection,
+                TextElement::Answer,
+                metrics.answer_size,
+            );
+        }
+    }
+
+    warnings
+}
+
+// Renders a block of text centred and word-wrapped into a rectangle, shrinking it if it doesn't
+// fit at full size. The text can have markup, as described in the markup module.
 fn render_text<'a>(
     text: &'a str,
-    font: &Font,
+    fonts: &ScalableFont,
     width: u32,
     height: u32,
     padding: u32,
     colour: Color,
 ) -> Result<Surface<'a>, String> {
     let text_width = width - 2 * padding;
-    let splits = split_text(text, font, text_width);
+    let paragraphs = markup::parse(text);
+    let font = fonts.fit(&paragraphs, text_width, height)?.font;
+    let lines = wrap_text(&paragraphs, font, text_width);
 
     let mut output_surface = Surface::new(width, height, sdl2::pixels::PixelFormatEnum::RGBA8888)?;
 
-    let text_height = splits.len() as i32 * font.recommended_line_spacing();
+    let text_height = lines.len() as i32 * font.recommended_line_spacing();
     let y_start = (height as i32 - text_height) / 2;
 
-    for (i, text_line) in splits.into_iter().enumerate() {
-        let rendered_line = match font.render(text_line).blended(colour) {
-            Ok(s) => s,
-            Err(sdl2::ttf::FontError::InvalidLatin1Text(_)) => unreachable!(),
-            Err(sdl2::ttf::FontError::SdlError(s)) => return Err(s),
-        };
+    for (i, text_line) in lines.iter().enumerate() {
+        if text_line.is_empty() {
+            continue;
+        }
+        let (rendered_line, baseline) = font.render(text_line, colour)?;
 
-        // position each line centred
+        // position each line centred, with its baseline where a plain line's would be
         let dst_rect = Rect::new(
             (width as i32 - rendered_line.width() as i32) / 2,
-            y_start + i as i32 * font.recommended_line_spacing(),
+            y_start + i as i32 * font.recommended_line_spacing() + font.normal.ascent() - baseline,
             rendered_line.width(),
             rendered_line.height(),
         );
@@ -1101,70 +2328,233 @@ fn render_text<'a>(
     Ok(output_surface)
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum PrevChar {
-    NoChar,
-    Space,
-    NotSpace,
+// Wraps each paragraph of text to the width, keeping the empty lines between paragraphs
+fn wrap_text(paragraphs: &[markup::Line], font: SizedFont, width: u32) -> Vec<markup::Line> {
+    let mut lines = vec![];
+    for paragraph in paragraphs {
+        let wrapped = split_text(paragraph, font, width);
+        if wrapped.is_empty() {
+            lines.push(vec![]);
+        } else {
+            lines.extend(wrapped);
+        }
+    }
+    lines
 }
 
-// greedy algorithm
-fn split_text<'a>(text: &'a str, font: &Font, width: u32) -> Vec<&'a str> {
-    // find indices of word starts and word ends
+// greedy algorithm. Words too wide for a line on their own are broken with a hyphen.
+fn split_text(text: &[markup::Span], font: SizedFont, width: u32) -> Vec<markup::Line> {
+    split_measured_text(text, &|spans| font.width_of(spans).unwrap(), width)
+}
 
-    let mut word_starts = vec![];
-    let mut word_ends = vec![];
+// split_text, measuring text with any function rather than a font
+fn split_measured_text(
+    text: &[markup::Span],
+    width_of: &dyn Fn(&[markup::Span]) -> u32,
+    width: u32,
+) -> Vec<markup::Line> {
+    let mut words = markup::words(text);
 
-    let mut prev = PrevChar::NoChar;
+    let mut output_vec = Vec::new();
 
-    for (i, c) in text.char_indices() {
-        match prev {
-            PrevChar::NoChar | PrevChar::Space => {
-                if c != ' ' {
-                    word_starts.push(i);
-                }
+    let mut i = 0;
+
+    while i < words.len() {
+        if width_of(&words[i].spans) > width {
+            let (line, rest) = break_word(&words[i].spans, width_of, width);
+            output_vec.push(line);
+            if rest.is_empty() {
+                i += 1;
+            } else {
+                words[i].spans = rest;
             }
-            PrevChar::NotSpace => {
-                if c == ' ' {
-                    word_ends.push(i);
-                }
+            continue;
+        }
+
+        // find the longest run of words that will fit in the width allowed, with the spaces
+        // between them in the style they were written in
+        let mut line = words[i].spans.clone();
+        let mut j = i + 1;
+        while j < words.len() {
+            let mut longer = line.clone();
+            markup::push_text(&mut longer, " ", words[j].space_style);
+            for span in &words[j].spans {
+                markup::push_text(&mut longer, &span.text, span.style);
             }
+            if width_of(&longer) <= width {
+                line = longer;
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        output_vec.push(line);
+
+        i = j;
+    }
+
+    output_vec
+}
+
+// Breaks a word that's too wide for a line, returning as much of it as fits with a hyphen, and
+// the rest of the word
+fn break_word(
+    word: &[markup::Span],
+    width_of: &dyn Fn(&[markup::Span]) -> u32,
+    width: u32,
+) -> (markup::Line, markup::Line) {
+    let chars: Vec<_> = word
+        .iter()
+        .flat_map(|span| span.text.chars().map(move |c| (c, span.style)))
+        .collect();
+    let to_line = |chars: &[(char, markup::Style)]| {
+        let mut line = vec![];
+        for &(c, style) in chars {
+            markup::push_text(&mut line, c.encode_utf8(&mut [0; 4]), style);
         }
+        line
+    };
 
-        if c == ' ' {
-            prev = PrevChar::Space;
+    // at least one character has to go on the line, even if it doesn't fit
+    let mut end = 1.min(chars.len());
+    let mut broken = to_line(&chars[..end]);
+
+    for candidate_end in 1..chars.len() {
+        let mut line = to_line(&chars[..candidate_end]);
+        markup::push_text(&mut line, "-", chars[candidate_end - 1].1);
+        if width_of(&line) <= width {
+            broken = line;
+            end = candidate_end;
         } else {
-            prev = PrevChar::NotSpace
+            break;
         }
     }
 
-    if prev == PrevChar::NotSpace {
-        word_ends.push(text.len());
+    (broken, to_line(&chars[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW_SIZES: [(u32, u32); 7] = [
+        (640, 360),
+        (1280, 720),
+        (1366, 768),
+        (1920, 1080),
+        (3840, 2160),
+        (1280, 1024),
+        (2560, 720),
+    ];
+
+    #[test]
+    fn layout_fits_in_the_window() {
+        for &size in WINDOW_SIZES.iter() {
+            let metrics = Metrics::from_window_dimensions(size);
+            let last_tile = metrics.get_tile_dest_rect(3);
+            let answer = metrics.get_answer_dest_rect();
+
+            assert!(last_tile.right() <= size.0 as i32, "{:?}", size);
+            assert!(answer.bottom() <= size.1 as i32, "{:?}", size);
+            assert!(last_tile.bottom() < answer.top(), "{:?}", size);
+            assert_eq!(last_tile.right(), answer.right(), "{:?}", size);
+            assert!(metrics.text_size > 0, "{:?}", size);
+        }
     }
 
-    assert_eq!(word_starts.len(), word_ends.len());
+    #[test]
+    fn layout_is_centred() {
+        for &size in WINDOW_SIZES.iter() {
+            let metrics = Metrics::from_window_dimensions(size);
+            let answer = metrics.get_answer_dest_rect();
+            let left_gap = answer.left();
+            let right_gap = size.0 as i32 - answer.right();
+            assert!((left_gap - right_gap).abs() <= 1, "{:?}", size);
+        }
+    }
 
-    let mut output_vec = Vec::new();
+    #[test]
+    fn score_tiles_and_banner_stay_above_the_tiles() {
+        for &size in WINDOW_SIZES.iter() {
+            let metrics = Metrics::from_window_dimensions(size);
+            let left = metrics.left_score_tile_rect;
+            let right = metrics.right_score_tile_rect;
+            let banner = metrics.buzz_banner_rect;
+
+            assert!(left.bottom() <= metrics.progress_bar_y, "{:?}", size);
+            assert!(left.right() <= banner.left(), "{:?}", size);
+            assert!(banner.right() <= right.left(), "{:?}", size);
+        }
+    }
 
-    let mut i = 0;
+    #[test]
+    fn pictures_keep_their_shape_and_follow_the_tile() {
+        let metrics = Metrics::from_window_dimensions((1280, 720));
+        let tile = metrics.get_tile_dest_rect(0);
+
+        let square = metrics.get_scaled_tile_dest_rect(tile, (100, 100));
+        assert_eq!(square.height(), tile.height());
+        assert_eq!(square.width(), tile.height());
+        assert!((square.center().x() - tile.center().x()).abs() <= 1);
+        assert_eq!(square.center().y(), tile.center().y());
+
+        // half way through flipping over, the picture is half as wide too
+        let flipping = Rect::from_center(tile.center(), tile.width() / 2, tile.height());
+        let squashed = metrics.get_scaled_tile_dest_rect(flipping, (100, 100));
+        assert_eq!(squashed.height(), tile.height());
+        assert!((squashed.width() as i32 - square.width() as i32 / 2).abs() <= 1);
+    }
 
-    while i < word_starts.len() {
-        // find the longest run of words that will fit in the width allowed
-        let mut j = i;
-        for jx in i..word_ends.len() {
-            let slice = &text[word_starts[i]..word_ends[jx]];
-            let computed_length = font.size_of(slice).unwrap().0;
-            if computed_length <= width {
-                j = jx;
-            } else {
-                break;
-            }
-        }
+    // every character is 10 pixels wide
+    fn monospaced(spans: &[markup::Span]) -> u32 {
+        spans
+            .iter()
+            .map(|span| span.text.chars().count() as u32)
+            .sum::<u32>()
+            * 10
+    }
 
-        output_vec.push(&text[word_starts[i]..word_ends[j]]);
+    fn split(text: &str, width: u32) -> Vec<String> {
+        let paragraph = &markup::parse(text)[0];
+        split_measured_text(paragraph, &monospaced, width)
+            .iter()
+            .map(|line| markup::line_text(line))
+            .collect()
+    }
 
-        i = j + 1;
+    #[test]
+    fn words_wrap_at_the_width() {
+        assert_eq!(
+            split("The quick brown fox", 100),
+            ["The quick", "brown fox"]
+        );
+        assert_eq!(split("The quick brown fox", 190), ["The quick brown fox"]);
+        assert_eq!(split("a b c", 10), ["a", "b", "c"]);
     }
 
-    output_vec
+    #[test]
+    fn words_too_long_for_a_line_are_hyphenated() {
+        assert_eq!(
+            split("Floccinaucinihilipilification", 100),
+            ["Floccinau-", "cinihilip-", "ilificati-", "on"]
+        );
+        assert_eq!(split("a abcdefg", 50), ["a", "abcd-", "efg"]);
+        // a line always has at least one character on it
+        assert_eq!(split("ab", 5), ["a", "b"]);
+    }
+
+    #[test]
+    fn wrapping_keeps_styles() {
+        let paragraph = &markup::parse("*Hamlet* and **Macbeth**")[0];
+        let lines = split_measured_text(paragraph, &monospaced, 1000);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(&lines[0], paragraph);
+
+        let lines = split_measured_text(paragraph, &monospaced, 100);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0][0].style.italic);
+        assert_eq!(markup::line_text(&lines[1]), "Macbeth");
+        assert!(lines[1][0].style.bold);
+    }
 }
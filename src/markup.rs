@@ -0,0 +1,346 @@
+use sdl2::ttf::FontStyle;
+
+/// Characters that can be escaped with a backslash to stop them being read as markup
+const ESCAPABLE: &str = "*^_~\\{}";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Script {
+    Normal,
+    Super,
+    Sub,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub script: Script,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            bold: false,
+            italic: false,
+            script: Script::Normal,
+        }
+    }
+}
+
+impl Style {
+    pub fn font_style(self) -> FontStyle {
+        let mut font_style = FontStyle::NORMAL;
+        if self.bold {
+            font_style |= FontStyle::BOLD;
+        }
+        if self.italic {
+            font_style |= FontStyle::ITALIC;
+        }
+        font_style
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// A line of text, as spans in different styles
+pub type Line = Vec<Span>;
+
+/// A word along with the style of the space before it, so that a space in the middle of styled
+/// text keeps its style when the line is put back together
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub space_style: Style,
+    pub spans: Line,
+}
+
+/// Parses the markup in clue and connection text into lines, one for each explicit line break:
+///
+/// - `*italic*` and `**bold**`
+/// - `^{superscript}`, or `^x` for a single character, and `_{subscript}`
+/// - `\n` for a line break and `~` for a non-breaking space
+/// - a backslash before any of `*^_~\{}` for the character itself
+///
+/// Anything left open carries on to the end of the text, and any other character is itself, so
+/// plain text like `_____ book` reads the same as before.
+pub fn parse(text: &str) -> Vec<Line> {
+    let mut lines = vec![vec![]];
+    let mut style = Style::default();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let line = lines.last_mut().unwrap();
+        match c {
+            '\\' => match chars.peek() {
+                Some('n') => {
+                    chars.next();
+                    push_text(line, &current, style);
+                    current.clear();
+                    lines.push(vec![]);
+                }
+                Some(&escaped) if ESCAPABLE.contains(escaped) => {
+                    chars.next();
+                    current.push(escaped);
+                }
+                _ => current.push(c),
+            },
+            '*' => {
+                push_text(line, &current, style);
+                current.clear();
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    style.bold = !style.bold;
+                } else {
+                    style.italic = !style.italic;
+                }
+            }
+            '^' | '_' if style.script == Script::Normal && chars.peek() == Some(&'{') => {
+                chars.next();
+                push_text(line, &current, style);
+                current.clear();
+                style.script = if c == '^' { Script::Super } else { Script::Sub };
+            }
+            '}' if style.script != Script::Normal => {
+                push_text(line, &current, style);
+                current.clear();
+                style.script = Script::Normal;
+            }
+            '^' if style.script == Script::Normal
+                && chars.peek().is_some_and(|next| !next.is_whitespace()) =>
+            {
+                push_text(line, &current, style);
+                current.clear();
+                let superscript = chars.next().unwrap().to_string();
+                let superscript_style = Style {
+                    script: Script::Super,
+                    ..style
+                };
+                push_text(line, &superscript, superscript_style);
+            }
+            '~' => current.push('\u{a0}'),
+            _ => current.push(c),
+        }
+    }
+    push_text(lines.last_mut().unwrap(), &current, style);
+
+    lines
+}
+
+/// The text without its markup, for places that can only show plain text
+pub fn plain_text(text: &str) -> String {
+    parse(text)
+        .iter()
+        .map(|line| line_text(line))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The text as HTML, with its markup turned into the equivalent tags
+pub fn html(text: &str) -> String {
+    let mut html = String::new();
+    for (i, line) in parse(text).iter().enumerate() {
+        if i > 0 {
+            html.push_str("<br>");
+        }
+        for span in line {
+            let mut tags = vec![];
+            if span.style.bold {
+                tags.push("b");
+            }
+            if span.style.italic {
+                tags.push("i");
+            }
+            match span.style.script {
+                Script::Normal => {}
+                Script::Super => tags.push("sup"),
+                Script::Sub => tags.push("sub"),
+            }
+
+            for tag in &tags {
+                html.push_str(&format!("<{}>", tag));
+            }
+            html.push_str(&escape_html(&span.text).replace('\u{a0}', "&nbsp;"));
+            for tag in tags.iter().rev() {
+                html.push_str(&format!("</{}>", tag));
+            }
+        }
+    }
+    html
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Text with no markup as a line
+pub fn unstyled(text: &str) -> Line {
+    let mut line = vec![];
+    push_text(&mut line, text, Style::default());
+    line
+}
+
+pub fn line_text(line: &[Span]) -> String {
+    line.iter().map(|span| span.text.as_str()).collect()
+}
+
+/// Adds text to the end of a line, joining it onto the last span if that's in the same style
+pub fn push_text(line: &mut Line, text: &str, style: Style) {
+    if text.is_empty() {
+        return;
+    }
+    match line.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => line.push(Span {
+            text: text.to_string(),
+            style,
+        }),
+    }
+}
+
+/// Splits a line into words at its spaces. Non-breaking spaces don't split words.
+pub fn words(line: &[Span]) -> Vec<Word> {
+    let mut words = vec![];
+    let mut current = Word {
+        space_style: Style::default(),
+        spans: vec![],
+    };
+
+    for span in line {
+        for c in span.text.chars() {
+            if c == ' ' {
+                if !current.spans.is_empty() {
+                    words.push(current);
+                }
+                current = Word {
+                    space_style: span.style,
+                    spans: vec![],
+                };
+            } else {
+                let mut buffer = [0; 4];
+                push_text(&mut current.spans, c.encode_utf8(&mut buffer), span.style);
+            }
+        }
+    }
+    if !current.spans.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, bold: bool, italic: bool, script: Script) -> Span {
+        Span {
+            text: text.to_string(),
+            style: Style {
+                bold,
+                italic,
+                script,
+            },
+        }
+    }
+
+    fn plain(text: &str) -> Span {
+        span(text, false, false, Script::Normal)
+    }
+
+    #[test]
+    fn bold_and_italic() {
+        assert_eq!(
+            parse("a *b* **c** ***d***"),
+            vec![vec![
+                plain("a "),
+                span("b", false, true, Script::Normal),
+                plain(" "),
+                span("c", true, false, Script::Normal),
+                plain(" "),
+                span("d", true, true, Script::Normal),
+            ]]
+        );
+    }
+
+    #[test]
+    fn anything_left_open_carries_on_to_the_end() {
+        assert_eq!(
+            parse("a *b c"),
+            vec![vec![plain("a "), span("b c", false, true, Script::Normal)]]
+        );
+        assert_eq!(
+            parse("H_{2"),
+            vec![vec![plain("H"), span("2", false, false, Script::Sub)]]
+        );
+    }
+
+    #[test]
+    fn superscripts_and_subscripts() {
+        assert_eq!(
+            parse("x^2 + H_{2}O + e^{i pi}"),
+            vec![vec![
+                plain("x"),
+                span("2", false, false, Script::Super),
+                plain(" + H"),
+                span("2", false, false, Script::Sub),
+                plain("O + e"),
+                span("i pi", false, false, Script::Super),
+            ]]
+        );
+    }
+
+    #[test]
+    fn markup_characters_on_their_own_are_plain_text() {
+        assert_eq!(parse("_____ book"), vec![vec![plain("_____ book")]]);
+        assert_eq!(parse("a ^ b }"), vec![vec![plain("a ^ b }")]]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse(r"\*a\* \^{b} \_{c\} \~ \\ \q"),
+            vec![vec![plain(r"*a* ^{b} _{c} ~ \ \q")]]
+        );
+        assert_eq!(
+            parse(r"one\ntwo\\n"),
+            vec![vec![plain("one")], vec![plain(r"two\n")]]
+        );
+    }
+
+    #[test]
+    fn styles_carry_across_line_breaks() {
+        assert_eq!(
+            parse(r"*a\nb*"),
+            vec![
+                vec![span("a", false, true, Script::Normal)],
+                vec![span("b", false, true, Script::Normal)],
+            ]
+        );
+    }
+
+    #[test]
+    fn words_split_at_spaces_but_not_non_breaking_ones() {
+        let line = &parse("*a b* c~d  e")[0];
+        let words = words(line);
+        let texts: Vec<String> = words.iter().map(|w| line_text(&w.spans)).collect();
+        assert_eq!(texts, vec!["a", "b", "c\u{a0}d", "e"]);
+        assert!(words[1].space_style.italic);
+        assert!(!words[2].space_style.italic);
+    }
+
+    #[test]
+    fn html_and_plain_text() {
+        assert_eq!(
+            html(r"**a** <b> & x^2\n*c*~d"),
+            "<b>a</b> &lt;b&gt; &amp; x<sup>2</sup><br><i>c</i>&nbsp;d"
+        );
+        assert_eq!(plain_text(r"**a** b\nc_{1}"), "a b c1");
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Instant;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::video::{Window, WindowContext};
+use sdl2::VideoSubsystem;
+
+use crate::buzzers::Team;
+use crate::controls::{Action, Controls};
+use crate::fonts::{FontChain, SizedFont};
+use crate::markup;
+use crate::questions::QuestionType;
+use crate::snapshot::GameSnapshot;
+use crate::texture_cache::TextureCache;
+use crate::FONT_RELOAD_DELAY;
+
+const PRESENTER_WINDOW_SIZE: (u32, u32) = (960, 540);
+const BACKGROUND_COLOUR: Color = Color::RGB(0x22, 0x22, 0x22);
+const TEXT_COLOUR: Color = Color::RGB(0xff, 0xff, 0xff);
+const UPCOMING_CLUE_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
+const ANSWER_COLOUR: Color = Color::RGB(0xff, 0xcc, 0x66);
+const NOTE_COLOUR: Color = Color::RGB(0x99, 0xcc, 0xff);
+const HINT_COLOUR: Color = Color::RGB(0x99, 0x99, 0x99);
+const RED_TEAM_COLOUR: Color = Color::RGB(0xcc, 0x88, 0x88);
+const BLUE_TEAM_COLOUR: Color = Color::RGB(0x88, 0x88, 0xff);
+
+/// Actions whose keys are listed along the bottom of the presenter view
+const HINT_ACTIONS: [Action; 6] = [
+    Action::Next,
+    Action::Stop,
+    Action::Correct,
+    Action::Incorrect,
+    Action::Undo,
+    Action::Pause,
+];
+
+/// A second window for the host, showing everything the audience can't see yet
+pub struct Presenter<'ttf> {
+    canvas: Canvas<Window>,
+    ttf_context: &'ttf Sdl2TtfContext,
+    font_paths: Vec<String>,
+    font: FontChain<'ttf>,
+    line_height: i32,
+    margin: i32,
+    /// The window height when it was last drawn
+    height: u32,
+    /// When the window was last resized, while the font is still the size it was before
+    resized_at: Option<Instant>,
+}
+
+impl<'ttf> Presenter<'ttf> {
+    pub fn new(
+        video_subsystem: &VideoSubsystem,
+        ttf_context: &'ttf Sdl2TtfContext,
+        font_paths: &[String],
+    ) -> Result<Self, String> {
+        let window = video_subsystem
+            .window(
+                "Only Connect Trivia - Presenter",
+                PRESENTER_WINDOW_SIZE.0,
+                PRESENTER_WINDOW_SIZE.1,
+            )
+            .allow_highdpi()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let canvas = window
+            .into_canvas()
+            .accelerated()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let (_, height) = canvas.output_size()?;
+        let font = load_font(ttf_context, font_paths, height)?;
+        let line_height = font.recommended_line_spacing();
+
+        Ok(Presenter {
+            canvas,
+            ttf_context,
+            font_paths: font_paths.to_vec(),
+            font,
+            line_height,
+            margin: line_height / 2,
+            height,
+            resized_at: None,
+        })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// For making the texture cache to draw with
+    pub fn texture_creator(&self) -> TextureCreator<WindowContext> {
+        self.canvas.texture_creator()
+    }
+
+    /// When the window was last resized, if it's still waiting to reload the font for its size
+    pub fn resized_at(&self) -> Option<Instant> {
+        self.resized_at
+    }
+
+    pub fn draw(
+        &mut self,
+        snapshot: &GameSnapshot,
+        controls: &Controls,
+        textures: &mut TextureCache<'_, WindowContext>,
+    ) -> Result<(), String> {
+        let (width, height) = self.canvas.output_size()?;
+        if height != self.height {
+            self.height = height;
+            self.resized_at = Some(Instant::now());
+        }
+        if self
+            .resized_at
+            .is_some_and(|at| at.elapsed() >= FONT_RELOAD_DELAY)
+        {
+            self.font = load_font(self.ttf_context, &self.font_paths, height)?;
+            self.line_height = self.font.recommended_line_spacing();
+            self.margin = self.line_height / 2;
+            textures.clear();
+            self.resized_at = None;
+        }
+
+        self.canvas.set_draw_color(BACKGROUND_COLOUR);
+        self.canvas.clear();
+
+        // scores and the set title along the top
+        let mut y = self.margin;
+        self.draw_line(
+            textures,
+            &format!("Red {}", snapshot.red_score),
+            RED_TEAM_COLOUR,
+            self.margin,
+            y,
+        )?;
+        let blue_score = format!("Blue {}", snapshot.blue_score);
+        let blue_score_width = self.font.size_of(&blue_score)?.0;
+        self.draw_line(
+            textures,
+            &blue_score,
+            BLUE_TEAM_COLOUR,
+            width as i32 - self.margin - blue_score_width as i32,
+            y,
+        )?;
+        if let Some(ref title) = snapshot.set_title {
+            let title_width = self.font.size_of(title)?.0;
+            self.draw_line(
+                textures,
+                title,
+                TEXT_COLOUR,
+                (width as i32 - title_width as i32) / 2,
+                y,
+            )?;
+        }
+        y += self.line_height * 2;
+
+        self.draw_line(
+            textures,
+            &status_line(snapshot),
+            TEXT_COLOUR,
+            self.margin,
+            y,
+        )?;
+        y += self.line_height * 2;
+
+        let is_sequence = snapshot.question_type == Some(QuestionType::Sequence);
+
+        for (i, clue) in snapshot.clues.iter().enumerate() {
+            let revealed = i < snapshot.clues_shown && !(i == 3 && snapshot.fourth_clue_hidden);
+            let (text, colour) = if is_sequence && i == 3 {
+                (format!("4. {} (fourth item)", clue), ANSWER_COLOUR)
+            } else if revealed {
+                (format!("{}. {}", i + 1, clue), TEXT_COLOUR)
+            } else {
+                (format!("{}. {}", i + 1, clue), UPCOMING_CLUE_COLOUR)
+            };
+            y = self.draw_wrapped(textures, &text, colour, y, width)?;
+        }
+
+        if let Some(ref connection) = snapshot.connection {
+            y += self.line_height / 2;
+            y = self.draw_wrapped(
+                textures,
+                &format!("Connection: {}", connection),
+                ANSWER_COLOUR,
+                y,
+                width,
+            )?;
+        }
+
+        if !snapshot.notes.is_empty() {
+            y += self.line_height / 2;
+            for note in &snapshot.notes {
+                y = self.draw_wrapped(textures, note, NOTE_COLOUR, y, width)?;
+            }
+        }
+
+        let hints: Vec<String> = HINT_ACTIONS
+            .iter()
+            .filter_map(|&action| {
+                let keys = controls.key_names(action);
+                if keys.is_empty() {
+                    None
+                } else {
+                    Some(format!("{}: {}", action.description(), keys.join("/")))
+                }
+            })
+            .collect();
+        self.draw_wrapped(
+            textures,
+            &hints.join("   "),
+            HINT_COLOUR,
+            height as i32 - self.margin - self.line_height,
+            width,
+        )?;
+
+        self.canvas.present();
+        Ok(())
+    }
+
+    fn draw_line(
+        &mut self,
+        textures: &mut TextureCache<'_, WindowContext>,
+        text: &str,
+        colour: Color,
+        x: i32,
+        y: i32,
+    ) -> Result<(), String> {
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let texture = textures.line(text, &self.font, colour)?;
+        let query = texture.query();
+        self.canvas
+            .copy(texture, None, Rect::new(x, y, query.width, query.height))
+    }
+
+    /// Draws left-aligned text wrapped to the window width, returning the y position of the next
+    /// line
+    fn draw_wrapped(
+        &mut self,
+        textures: &mut TextureCache<'_, WindowContext>,
+        text: &str,
+        colour: Color,
+        mut y: i32,
+        width: u32,
+    ) -> Result<i32, String> {
+        let text_width = width - 2 * self.margin as u32;
+        let font = SizedFont::plain(&self.font);
+        for line in crate::split_text(&markup::unstyled(text), font, text_width) {
+            self.draw_line(textures, &markup::line_text(&line), colour, self.margin, y)?;
+            y += self.line_height;
+        }
+        Ok(y)
+    }
+}
+
+/// The font for a window this high, with room for about 20 lines of text
+fn load_font<'ttf>(
+    ttf_context: &'ttf Sdl2TtfContext,
+    font_paths: &[String],
+    height: u32,
+) -> Result<FontChain<'ttf>, String> {
+    FontChain::load(ttf_context, font_paths, (height / 26).max(1) as u16)
+}
+
+fn status_line(snapshot: &GameSnapshot) -> String {
+    let mut parts = vec![];
+
+    match snapshot.screen {
+        "start" => parts.push("Start page".to_string()),
+        "title" => parts.push("Title page".to_string()),
+        "end" => parts.push("Game over".to_string()),
+        _ => {}
+    }
+
+    match snapshot.team_in_play {
+        Some(Team::Red) => parts.push("Red to play".to_string()),
+        Some(Team::Blue) => parts.push("Blue to play".to_string()),
+        None => {}
+    }
+    if snapshot.passed_over {
+        parts.push("passed over".to_string());
+    }
+    if let Some(points) = snapshot.points_available {
+        parts.push(format!("{} points", points));
+    }
+    if let Some(seconds) = snapshot.seconds_remaining {
+        parts.push(format!("{} s left", seconds));
+    }
+    if snapshot.paused {
+        parts.push("paused".to_string());
+    } else if snapshot.clock_stopped {
+        parts.push("clock stopped".to_string());
+    }
+    if snapshot.answer_shown {
+        parts.push("answer shown".to_string());
+    }
+    if let Some(ref buzz) = snapshot.buzz {
+        parts.push(format!("{:?} buzzed ({:.3} s)", buzz.team, buzz.seconds));
+        if let Some(overturned) = buzz.overturned {
+            parts.push(format!("corrected from {:?}", overturned));
+        }
+    }
+
+    parts.join(", ")
+}
@@ -0,0 +1,325 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use sdl2::image::{LoadSurface, SaveSurface};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget};
+use sdl2::surface::Surface;
+use sdl2::ttf::Sdl2TtfContext;
+
+use crate::animation::AnimationFrame;
+use crate::questions::QuestionSet;
+use crate::texture_cache::TextureCache;
+use crate::theme::{ElementFonts, Theme};
+use crate::{Metrics, QuestionPhase, QuestionState};
+
+/// Screens are shrunk by this much on contact sheets
+const THUMBNAIL_SCALE: u32 = 4;
+const CONTACT_SHEET_GAP: u32 = 8;
+const CONTACT_SHEET_BACKGROUND: Color = Color::RGB(0x22, 0x22, 0x22);
+/// How far apart two pixels' colour channels can be and still match, so that slightly different
+/// antialiasing from another version of SDL_ttf doesn't fail a comparison
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// Every screen of a game, rendered offscreen
+pub struct Screens {
+    start: Surface<'static>,
+    sets: Vec<SetScreens>,
+    end: Surface<'static>,
+}
+
+struct SetScreens {
+    // the title page on its own, then a row of screens for each question
+    rows: Vec<Vec<(String, Surface<'static>)>>,
+}
+
+/// Renders the start page, then for each set its title page and every stage of each question,
+/// then the end page. Questions are shown with the clock stopped at the start, so the same
+/// questions always give the same images.
+pub fn render_screens(
+    ttf_context: &Sdl2TtfContext,
+    font_paths: &[String],
+    theme: &Theme,
+    question_sets: Vec<QuestionSet>,
+    size: (u32, u32),
+) -> Result<Screens, String> {
+    let metrics = Metrics::from_window_dimensions(size);
+    let fonts = theme.load_fonts(ttf_context, font_paths, metrics.text_size)?;
+    let mut canvas = Surface::new(size.0, size.1, PixelFormatEnum::RGB888)?.into_canvas()?;
+    let texture_creator = canvas.texture_creator();
+    let mut textures = TextureCache::new(&texture_creator);
+
+    let mut draw = |textures: &mut TextureCache<_>, question_state: &QuestionState| {
+        render_screen(
+            &mut canvas,
+            textures,
+            theme,
+            &fonts,
+            &metrics,
+            question_state,
+        )
+    };
+
+    let start = draw(&mut textures, &QuestionState::StartPage)?;
+
+    let mut sets = vec![];
+    for set in question_sets {
+        let title_page = QuestionState::TitlePage { title: set.title };
+        let mut rows = vec![vec![(
+            "title".to_string(),
+            draw(&mut textures, &title_page)?,
+        )]];
+
+        for (i, question) in set.questions.into_iter().enumerate() {
+            let name = format!("q{:02}", i + 1);
+            let started = Instant::now();
+            let mut question_state = QuestionState::new_question(question, i % 2 == 0, started);
+            if let QuestionState::Question {
+                ref mut stopped, ..
+            } = question_state
+            {
+                *stopped = Some(started);
+            }
+
+            let mut row = vec![];
+            loop {
+                let phase = question_phase(&mut question_state);
+                let clues_shown = phase.clues_to_show();
+                phase.next();
+                if phase.clues_to_show() == clues_shown {
+                    break;
+                }
+                let screen_name = format!("{}-clue{}", name, row.len() + 1);
+                row.push((screen_name, draw(&mut textures, &question_state)?));
+            }
+            question_phase(&mut question_state).pass_over();
+            row.push((
+                format!("{}-passed-over", name),
+                draw(&mut textures, &question_state)?,
+            ));
+            question_phase(&mut question_state).show_answer();
+            row.push((
+                format!("{}-answer", name),
+                draw(&mut textures, &question_state)?,
+            ));
+
+            rows.push(row);
+            // pictures are cached by clue number, so can't be kept for the next question
+            textures.clear();
+        }
+
+        sets.push(SetScreens { rows });
+    }
+
+    let end = draw(&mut textures, &QuestionState::EndPage)?;
+
+    Ok(Screens { start, sets, end })
+}
+
+fn render_screen(
+    canvas: &mut Canvas<Surface<'static>>,
+    textures: &mut TextureCache<'_, <Surface<'static> as RenderTarget>::Context>,
+    theme: &Theme,
+    fonts: &ElementFonts,
+    metrics: &Metrics,
+    question_state: &QuestionState,
+) -> Result<Surface<'static>, String> {
+    crate::draw_screen(
+        canvas,
+        textures,
+        theme,
+        fonts,
+        metrics,
+        question_state,
+        (0, 0),
+        &AnimationFrame::settled(),
+    );
+    // draws are batched, and presenting is what flushes them to the surface
+    canvas.present();
+    canvas.surface().convert_format(PixelFormatEnum::RGB888)
+}
+
+/// The phase of a question being shown, which the state has to be
+pub fn question_phase(question_state: &mut QuestionState) -> &mut dyn QuestionPhase {
+    match question_state {
+        QuestionState::Question { phase, .. } => phase.as_mut(),
+        _ => unreachable!(),
+    }
+}
+
+impl Screens {
+    /// Each screen along with where it's saved, relative to the output directory
+    fn all(&self) -> Vec<(PathBuf, &Surface<'static>)> {
+        let mut screens = vec![(PathBuf::from("start.png"), &self.start)];
+        for (i, set) in self.sets.iter().enumerate() {
+            let set_dir = PathBuf::from(format!("set{:02}", i + 1));
+            for (name, surface) in set.rows.iter().flatten() {
+                screens.push((set_dir.join(format!("{}.png", name)), surface));
+            }
+        }
+        screens.push((PathBuf::from("end.png"), &self.end));
+        screens
+    }
+
+    /// Saves every screen as a PNG, along with a contact sheet for each set. Returns how many
+    /// screens were saved.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<usize, String> {
+        let dir = dir.as_ref();
+        let screens = self.all();
+
+        for (path, surface) in &screens {
+            let path = dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            surface.save(&path)?;
+        }
+        for (i, set) in self.sets.iter().enumerate() {
+            let path = dir
+                .join(format!("set{:02}", i + 1))
+                .join("contact-sheet.png");
+            set.contact_sheet()?.save(path)?;
+        }
+
+        Ok(screens.len())
+    }
+
+    /// Compares every screen with the image of the same name in a directory saved earlier, and
+    /// describes each one that's missing or different, and each saved screen that isn't rendered
+    /// any more
+    pub fn compare<P: AsRef<Path>>(&self, dir: P) -> Vec<String> {
+        let dir = dir.as_ref();
+        let mut mismatches = vec![];
+        let screens = self.all();
+
+        let rendered: HashSet<PathBuf> = screens.iter().map(|(path, _)| dir.join(path)).collect();
+        let mut golden_paths = vec![];
+        if let Err(e) = find_screens(dir, &mut golden_paths) {
+            mismatches.push(format!("{}: {}", dir.display(), e));
+        }
+        golden_paths.sort();
+        for golden_path in golden_paths {
+            if !rendered.contains(&golden_path) {
+                mismatches.push(format!(
+                    "{}: no screen is rendered for this any more",
+                    golden_path.display()
+                ));
+            }
+        }
+
+        for (path, surface) in screens {
+            let golden_path = dir.join(&path);
+            let result = Surface::from_file(&golden_path)
+                .and_then(|golden| count_different_pixels(surface, &golden));
+            match result {
+                Ok(0) => {}
+                Ok(count) => mismatches.push(format!(
+                    "{}: {} pixels differ",
+                    golden_path.display(),
+                    count
+                )),
+                Err(e) => mismatches.push(format!("{}: {}", golden_path.display(), e)),
+            }
+        }
+
+        mismatches
+    }
+}
+
+impl SetScreens {
+    /// Thumbnails of every screen in the set, a row per question
+    fn contact_sheet(&self) -> Result<Surface<'static>, String> {
+        let (screen_width, screen_height) = self.rows[0][0].1.size();
+        let thumbnail_size = (
+            screen_width / THUMBNAIL_SCALE,
+            screen_height / THUMBNAIL_SCALE,
+        );
+        let columns = self.rows.iter().map(|row| row.len()).max().unwrap_or(1) as u32;
+        let rows = self.rows.len() as u32;
+
+        let mut sheet = Surface::new(
+            columns * (thumbnail_size.0 + CONTACT_SHEET_GAP) + CONTACT_SHEET_GAP,
+            rows * (thumbnail_size.1 + CONTACT_SHEET_GAP) + CONTACT_SHEET_GAP,
+            PixelFormatEnum::RGB888,
+        )?;
+        sheet.fill_rect(None, CONTACT_SHEET_BACKGROUND)?;
+
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, (_name, surface)) in row.iter().enumerate() {
+                let dst_rect = Rect::new(
+                    (CONTACT_SHEET_GAP + x as u32 * (thumbnail_size.0 + CONTACT_SHEET_GAP)) as i32,
+                    (CONTACT_SHEET_GAP + y as u32 * (thumbnail_size.1 + CONTACT_SHEET_GAP)) as i32,
+                    thumbnail_size.0,
+                    thumbnail_size.1,
+                );
+                surface.blit_scaled(None, &mut sheet, dst_rect)?;
+            }
+        }
+
+        Ok(sheet)
+    }
+}
+
+/// Finds every saved screen under a directory, leaving out the contact sheets
+fn find_screens(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_screens(&path, paths)?;
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("png"))
+            && path
+                .file_name()
+                .is_some_and(|name| name != "contact-sheet.png")
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Counts the pixels where two images differ by more than the tolerance
+fn count_different_pixels(a: &Surface, b: &Surface) -> Result<usize, String> {
+    if a.size() != b.size() {
+        return Err(format!(
+            "Size is {}x{}, expected {}x{}",
+            a.width(),
+            a.height(),
+            b.width(),
+            b.height()
+        ));
+    }
+
+    // a byte per channel, so the comparison doesn't depend on byte order
+    let a = a.convert_format(PixelFormatEnum::RGB24)?;
+    let b = b.convert_format(PixelFormatEnum::RGB24)?;
+    let row_bytes = a.width() as usize * 3;
+    let (a_pitch, b_pitch) = (a.pitch() as usize, b.pitch() as usize);
+
+    let count = a.with_lock(|a_pixels| {
+        b.with_lock(|b_pixels| {
+            (0..a.height() as usize)
+                .map(|y| {
+                    let a_row = &a_pixels[y * a_pitch..][..row_bytes];
+                    let b_row = &b_pixels[y * b_pitch..][..row_bytes];
+                    a_row
+                        .chunks(3)
+                        .zip(b_row.chunks(3))
+                        .filter(|(a_pixel, b_pixel)| {
+                            a_pixel
+                                .iter()
+                                .zip(b_pixel.iter())
+                                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+                        })
+                        .count()
+                })
+                .sum()
+        })
+    });
+
+    Ok(count)
+}
@@ -7,11 +7,22 @@ use rand::prelude::*;
 use regex::Regex;
 use sdl2::image::LoadSurface;
 use sdl2::surface::Surface;
+use serde::{Deserialize, Serialize};
+
+use crate::history::History;
 
 lazy_static! {
     static ref PICTURE_CLUE_REGEX: Regex = Regex::new(r"^        picture: (\S+) (\S.+)$").unwrap();
 }
 
+/// Lines under a question that are read as something other than a clue, and what they're read as
+const NON_CLUE_LINES: [(&str, &str); 4] = [
+    ("note: ", "a note"),
+    ("author: ", "the author"),
+    ("difficulty: ", "the difficulty"),
+    ("tags: ", "tags"),
+];
+
 pub struct QuestionSet {
     pub title: String,
     pub questions: Vec<Question>,
@@ -22,9 +33,57 @@ pub struct Question {
     pub question_type: QuestionType,
     pub connection: String,
     pub clues: Vec<Clue>,
+    /// Notes for the host, never shown to the audience
+    pub notes: Vec<String>,
+    pub metadata: Metadata,
 }
 
-#[derive(Debug)]
+/// Details for choosing questions, never shown in the game
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub author: Option<String>,
+    pub difficulty: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Question {
+    /// A question with no clues yet
+    pub fn new(question_type: QuestionType, connection: String) -> Self {
+        Question {
+            question_type,
+            connection,
+            clues: Vec::new(),
+            notes: Vec::new(),
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// An ID that stays the same while the question's wording does, whichever file it's in or
+    /// however it's laid out. It's a hash of the type, connection and clues, ignoring case.
+    pub fn id(&self) -> String {
+        let question_type = match self.question_type {
+            QuestionType::Connection => "connection",
+            QuestionType::Sequence => "sequence",
+        };
+        let parts = std::iter::once(question_type)
+            .chain(std::iter::once(self.connection.as_str()))
+            .chain(self.clues.iter().map(Clue::text));
+
+        // 64-bit FNV-1a, which unlike the standard library's hasher won't change between releases
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for (i, part) in parts.enumerate() {
+            let separator = if i == 0 { "" } else { "\n" };
+            for byte in separator.bytes().chain(part.trim().to_lowercase().bytes()) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum QuestionType {
     Sequence,
     Connection,
@@ -32,22 +91,64 @@ pub enum QuestionType {
 
 pub enum Clue {
     TextClue(String),
-    PictureClue(Surface<'static>, String),
+    PictureClue(Picture, String),
     // MusicClues([;4]),
 }
 
+/// A picture for a clue, along with the file it came from
+pub struct Picture {
+    pub path: String,
+    pub image: Surface<'static>,
+}
+
+impl Picture {
+    pub fn load(path: &str) -> Result<Self, String> {
+        Ok(Picture {
+            path: path.into(),
+            image: Surface::from_file(path)?,
+        })
+    }
+}
+
+impl Clue {
+    /// The clue's text, or the caption for a picture clue
+    pub fn text(&self) -> &str {
+        match self {
+            Self::TextClue(clue) => clue,
+            Self::PictureClue(_picture, clue) => clue,
+        }
+    }
+}
+
 impl std::fmt::Debug for Clue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::TextClue(clue) => f.debug_tuple("TextClue").field(clue).finish(),
-            Self::PictureClue(_surface, clue) => f.debug_tuple("PictureClue").field(clue).finish(),
+            Self::PictureClue(_picture, clue) => f.debug_tuple("PictureClue").field(clue).finish(),
         }
     }
 }
 
-pub fn load_question_sets<P: AsRef<Path>>(path: P) -> Result<Vec<QuestionSet>, Vec<String>> {
+/// The question sets listed in a manifest, and who's playing
+pub struct Manifest {
+    pub question_sets: Vec<QuestionSet>,
+    /// The teams playing, which the history records for each game
+    pub teams: Vec<String>,
+}
+
+/// Loads the question sets listed in a manifest, shuffling the questions of each set with the
+/// random number generator, so the same seed always gives the same order. Questions the manifest
+/// excludes because of the history are left out before shuffling.
+pub fn load_manifest<P: AsRef<Path>, R: Rng>(
+    path: P,
+    history: &History,
+    rng: &mut R,
+) -> Result<Manifest, Vec<String>> {
     let mut errors = vec![];
-    let mut outputs = vec![];
+    let mut filenames = vec![];
+    let mut teams = vec![];
+    let mut exclude_recent = 0;
+    let mut exclude_teams = vec![];
     let question_sets_file = File::open(path).map_err(|e| vec![e.to_string()])?;
 
     let bufreader = BufReader::new(question_sets_file);
@@ -60,14 +161,16 @@ pub fn load_question_sets<P: AsRef<Path>>(path: P) -> Result<Vec<QuestionSet>, V
                 }
 
                 if let Some(filename) = l.strip_prefix("include_shuffle: ") {
-                    match load_questions(filename) {
-                        Ok(mut qs) => {
-                            // shuffle the questions
-                            qs.questions.shuffle(&mut rand::thread_rng());
-                            outputs.push(qs);
-                        }
-                        Err(mut es) => errors.append(&mut es),
+                    filenames.push(filename.to_string());
+                } else if let Some(names) = l.strip_prefix("teams: ") {
+                    teams = parse_names(names);
+                } else if let Some(count) = l.strip_prefix("exclude_recent: ") {
+                    match count.trim().parse() {
+                        Ok(n) => exclude_recent = n,
+                        Err(_) => errors.push(format!("Invalid game count {}", count)),
                     }
+                } else if let Some(names) = l.strip_prefix("exclude_teams: ") {
+                    exclude_teams = parse_names(names);
                 } else {
                     errors.push(format!("Unknown command {}", l));
                 }
@@ -78,18 +181,88 @@ pub fn load_question_sets<P: AsRef<Path>>(path: P) -> Result<Vec<QuestionSet>, V
         }
     }
 
+    let mut question_sets = vec![];
+    for filename in filenames {
+        match load_questions(&filename) {
+            Ok(mut qs) => {
+                let count = qs.questions.len();
+                qs.questions.retain(|q| {
+                    let id = q.id();
+                    !history.used_in_last_games(&id, exclude_recent)
+                        && !history.used_by_teams(&id, &exclude_teams)
+                });
+                let excluded = count - qs.questions.len();
+                if excluded > 0 {
+                    eprintln!(
+                        "Left out {} of {} questions from {} that have been played before",
+                        excluded, count, filename
+                    );
+                }
+                if qs.questions.is_empty() {
+                    continue;
+                }
+
+                // shuffle the questions
+                qs.questions.shuffle(rng);
+                question_sets.push(qs);
+            }
+            Err(mut es) => errors.append(&mut es),
+        }
+    }
+
     if errors.is_empty() {
-        Ok(outputs)
+        Ok(Manifest {
+            question_sets,
+            teams,
+        })
     } else {
         Err(errors)
     }
 }
 
+/// Splits a comma-separated list of team names
+fn parse_names(names: &str) -> Vec<String> {
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 pub fn load_questions<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String>> {
+    if is_spreadsheet(path.as_ref()) {
+        return crate::spreadsheet::load_spreadsheet(path);
+    }
+
+    let (question_set, errors) = read_questions(path.as_ref(), false)?;
+    if !errors.is_empty() {
+        Err(errors)
+    } else {
+        Ok(question_set)
+    }
+}
+
+/// Loads as much of a question file as can be read, along with the problems in the rest, so a
+/// badly laid out file can still be opened and fixed. Questions with the wrong number of clues
+/// are kept.
+pub fn load_questions_partially<P: AsRef<Path>>(
+    path: P,
+) -> Result<(QuestionSet, Vec<String>), Vec<String>> {
+    read_questions(path.as_ref(), true)
+}
+
+/// Reads a question file, returning the questions that could be read and the errors in the rest.
+/// Only a file that can't be read at all is an error.
+fn read_questions(
+    path: &Path,
+    keep_incomplete: bool,
+) -> Result<(QuestionSet, Vec<String>), Vec<String>> {
     let mut questions = Vec::new();
     let mut errors = Vec::new();
 
-    let mut current_question: Option<(QuestionType, String, Vec<Clue>)> = None;
+    // the question whose clues, notes and metadata are still being read
+    let mut current_question: Option<Question> = None;
 
     let file = match std::fs::File::open(path) {
         Ok(f) => f,
@@ -101,23 +274,20 @@ pub fn load_questions<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String
 
     let bufreader = BufReader::new(file);
 
-    let replace_question = |current_question: &mut Option<(QuestionType, String, Vec<Clue>)>,
+    let replace_question = |current_question: &mut Option<Question>,
                             new_question,
                             questions: &mut Vec<Question>,
                             errors: &mut Vec<String>| {
-        if let Some((question_type, connection, clues)) = current_question.take() {
-            if clues.len() == 4 {
-                questions.push(Question {
-                    question_type,
-                    connection,
-                    clues,
-                });
-            } else {
+        if let Some(question) = current_question.take() {
+            if question.clues.len() != 4 {
                 errors.push(format!(
                     "Incorrect number of prompts for connection: {}",
-                    connection
+                    question.connection
                 ));
             }
+            if question.clues.len() == 4 || keep_incomplete {
+                questions.push(question);
+            }
         }
 
         *current_question = new_question;
@@ -140,25 +310,53 @@ pub fn load_questions<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String
                 if let Some(q) = l.strip_prefix("    sequence: ") {
                     replace_question(
                         &mut current_question,
-                        Some((QuestionType::Sequence, q.to_string(), Vec::new())),
+                        Some(Question::new(QuestionType::Sequence, q.to_string())),
                         &mut questions,
                         &mut errors,
                     );
                 } else if let Some(q) = l.strip_prefix("    connection: ") {
                     replace_question(
                         &mut current_question,
-                        Some((QuestionType::Connection, q.to_string(), Vec::new())),
+                        Some(Question::new(QuestionType::Connection, q.to_string())),
                         &mut questions,
                         &mut errors,
                     );
+                } else if let Some(note) = l.strip_prefix("        note: ") {
+                    if let Some(ref mut question) = current_question {
+                        question.notes.push(note.into());
+                    } else {
+                        errors.push(format!("Note {} doesn't belong to a question", note));
+                    }
+                } else if let Some(author) = l.strip_prefix("        author: ") {
+                    if let Some(ref mut question) = current_question {
+                        question.metadata.author = Some(author.into());
+                    } else {
+                        errors.push(format!("Author {} doesn't belong to a question", author));
+                    }
+                } else if let Some(difficulty) = l.strip_prefix("        difficulty: ") {
+                    if let Some(ref mut question) = current_question {
+                        question.metadata.difficulty = Some(difficulty.into());
+                    } else {
+                        errors.push(format!(
+                            "Difficulty {} doesn't belong to a question",
+                            difficulty
+                        ));
+                    }
+                } else if let Some(tags) = l.strip_prefix("        tags: ") {
+                    if let Some(ref mut question) = current_question {
+                        question.metadata.tags = parse_tags(tags);
+                    } else {
+                        errors.push(format!("Tags {} don't belong to a question", tags));
+                    }
                 } else if let Some(captures) = PICTURE_CLUE_REGEX.captures(&l) {
                     let picture_path = captures.get(1).unwrap();
                     let text_clue = captures.get(2).unwrap();
 
                     // attempt to load the picture
-                    if let Ok(image) = Surface::from_file(picture_path.as_str()) {
-                        if let Some((_, _, ref mut clues)) = current_question {
-                            clues.push(Clue::PictureClue(image, text_clue.as_str().into()));
+                    if let Ok(picture) = Picture::load(picture_path.as_str()) {
+                        if let Some(ref mut question) = current_question {
+                            let caption = text_clue.as_str().into();
+                            question.clues.push(Clue::PictureClue(picture, caption));
                         } else {
                             errors.push(format!(
                                 "Clue {} doesn't belong to a question",
@@ -169,8 +367,8 @@ pub fn load_questions<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String
                         errors.push(format!("Could not load image {}", picture_path.as_str()));
                     }
                 } else if let Some(p) = l.strip_prefix("        ") {
-                    if let Some((_, _, ref mut clues)) = current_question {
-                        clues.push(Clue::TextClue(p.into()));
+                    if let Some(ref mut question) = current_question {
+                        question.clues.push(Clue::TextClue(p.into()));
                     } else {
                         errors.push(format!("Clue {} doesn't belong to a question", p));
                     }
@@ -187,9 +385,235 @@ pub fn load_questions<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String
     // close the last question
     replace_question(&mut current_question, None, &mut questions, &mut errors);
 
-    if !errors.is_empty() {
-        Err(errors)
+    Ok((QuestionSet { title, questions }, errors))
+}
+
+/// Whether a question file is a spreadsheet, rather than in the indented format
+pub fn is_spreadsheet(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()),
+        Some(ref e) if e == "csv" || e == "tsv"
+    )
+}
+
+/// Tags are written separated by commas
+pub fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Why a clue can't be written to a question file and read back as the same clue, if it can't
+pub fn clue_problem(clue: &Clue) -> Option<String> {
+    match clue {
+        Clue::TextClue(text) => {
+            if has_line_break(text) {
+                Some("has a line break, so it would be read back as more than one line".into())
+            } else if text.trim_start().starts_with('#') {
+                Some("starts with #, so it would be read back as a comment".into())
+            } else if let Some((prefix, read_as)) = NON_CLUE_LINES
+                .iter()
+                .find(|(prefix, _)| text.starts_with(prefix))
+            {
+                Some(format!(
+                    "starts with \"{}\", so it would be read back as {}",
+                    prefix.trim_end(),
+                    read_as
+                ))
+            } else if PICTURE_CLUE_REGEX.is_match(&format!("        {}", text)) {
+                Some("would be read back as a picture clue".into())
+            } else {
+                None
+            }
+        }
+        Clue::PictureClue(picture, caption) => {
+            let line = format!("        picture: {} {}", picture.path, caption);
+            if has_line_break(&line) {
+                return Some("has a line break in its picture path or caption".into());
+            }
+            match PICTURE_CLUE_REGEX.captures(&line) {
+                Some(captures) if captures[1] == picture.path && captures[2] == *caption => None,
+                _ if picture.path.contains(char::is_whitespace) => {
+                    Some(format!("has spaces in its picture path {}", picture.path))
+                }
+                _ => Some(
+                    "needs a caption of at least 2 characters, not starting with a space".into(),
+                ),
+            }
+        }
+    }
+}
+
+/// Why a question's connection, notes or details can't be written to a question file and read
+/// back as they are, if they can't. Each of them has to fit on its line.
+fn details_problem(question: &Question) -> Option<String> {
+    let metadata = &question.metadata;
+    if has_line_break(&question.connection) {
+        Some(format!(
+            "the connection {:?} has a line break",
+            question.connection
+        ))
+    } else if question.notes.iter().any(|note| has_line_break(note)) {
+        Some(format!(
+            "a note for {} has a line break",
+            question.connection
+        ))
+    } else if metadata.author.as_deref().is_some_and(has_line_break) {
+        Some(format!(
+            "the author of {} has a line break",
+            question.connection
+        ))
+    } else if metadata.difficulty.as_deref().is_some_and(has_line_break) {
+        Some(format!(
+            "the difficulty of {} has a line break",
+            question.connection
+        ))
+    } else if metadata
+        .tags
+        .iter()
+        .any(|tag| has_line_break(tag) || tag.contains(','))
+    {
+        Some(format!(
+            "a tag for {} has a comma or a line break",
+            question.connection
+        ))
     } else {
-        Ok(QuestionSet { title, questions })
+        None
+    }
+}
+
+fn has_line_break(text: &str) -> bool {
+    text.contains(['\n', '\r'])
+}
+
+/// Writes a question set in the format `load_questions` reads. Fails without writing anything if
+/// anything in it couldn't be read back as it is.
+pub fn save_questions<P: AsRef<Path>>(path: P, question_set: &QuestionSet) -> std::io::Result<()> {
+    let invalid = |problem| std::io::Error::new(std::io::ErrorKind::InvalidData, problem);
+    if has_line_break(&question_set.title) {
+        return Err(invalid("the title has a line break".to_string()));
+    }
+    for question in &question_set.questions {
+        if let Some(problem) = details_problem(question) {
+            return Err(invalid(problem));
+        }
+        for (i, clue) in question.clues.iter().enumerate() {
+            if let Some(problem) = clue_problem(clue) {
+                return Err(invalid(format!(
+                    "clue {} for {} {}",
+                    i + 1,
+                    question.connection,
+                    problem
+                )));
+            }
+        }
+    }
+
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+
+    writeln!(file, "{}", question_set.title)?;
+    for question in &question_set.questions {
+        let question_type = match question.question_type {
+            QuestionType::Connection => "connection",
+            QuestionType::Sequence => "sequence",
+        };
+        writeln!(file, "    {}: {}", question_type, question.connection)?;
+        for clue in &question.clues {
+            match clue {
+                Clue::TextClue(text) => writeln!(file, "        {}", text)?,
+                Clue::PictureClue(picture, caption) => {
+                    writeln!(file, "        picture: {} {}", picture.path, caption)?
+                }
+            }
+        }
+        for note in &question.notes {
+            writeln!(file, "        note: {}", note)?;
+        }
+        let metadata = &question.metadata;
+        if let Some(ref author) = metadata.author {
+            writeln!(file, "        author: {}", author)?;
+        }
+        if let Some(ref difficulty) = metadata.difficulty {
+            writeln!(file, "        difficulty: {}", difficulty)?;
+        }
+        if !metadata.tags.is_empty() {
+            writeln!(file, "        tags: {}", metadata.tags.join(", "))?;
+        }
+    }
+
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question_set() -> QuestionSet {
+        let mut connection = Question::new(QuestionType::Connection, "*Famous* ~ cats".into());
+        connection.clues = ["Tom", "Garfield", r"Felix\nthe Cat", "x^2 # not a comment"]
+            .iter()
+            .map(|&clue| Clue::TextClue(clue.into()))
+            .collect();
+        connection.notes = vec!["Accept any cartoon cat".into(), "note: twice".into()];
+        connection.metadata = Metadata {
+            author: Some("Ann".into()),
+            difficulty: Some("Easy".into()),
+            tags: vec!["animals".into(), "cartoons".into()],
+        };
+
+        let mut sequence = Question::new(QuestionType::Sequence, "Powers of two".into());
+        sequence.clues = ["1", "2", "4", "8"]
+            .iter()
+            .map(|&clue| Clue::TextClue(clue.into()))
+            .collect();
+
+        QuestionSet {
+            title: "Round one".into(),
+            questions: vec![connection, sequence],
+        }
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("questions-test-{}.txt", name))
+    }
+
+    #[test]
+    fn saved_questions_load_back_the_same() {
+        let path = temp_path("round-trip");
+        let question_set = question_set();
+        save_questions(&path, &question_set).unwrap();
+
+        let loaded = load_questions(&path).unwrap();
+        assert_eq!(loaded.title, question_set.title);
+        assert_eq!(
+            format!("{:?}", loaded.questions),
+            format!("{:?}", question_set.questions)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn questions_that_would_not_load_back_the_same_are_not_saved() {
+        let path = temp_path("refused");
+        let _ = std::fs::remove_file(&path);
+
+        let mut set = question_set();
+        set.questions[0].notes.push("two\nlines".into());
+        assert!(save_questions(&path, &set).is_err());
+
+        let mut set = question_set();
+        set.questions[1].connection = "Powers\r\nof two".into();
+        assert!(save_questions(&path, &set).is_err());
+
+        let mut set = question_set();
+        set.questions[0].clues[1] = Clue::TextClue("author: someone".into());
+        assert!(save_questions(&path, &set).is_err());
+
+        let mut set = question_set();
+        set.questions[0].metadata.tags.push("a, b".into());
+        assert!(save_questions(&path, &set).is_err());
+
+        assert!(!path.exists());
     }
 }
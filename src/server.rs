@@ -0,0 +1,320 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::IntoDeserializer;
+use serde::Deserialize;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::controls::Action;
+use crate::snapshot::GameSnapshot;
+
+const HOST_PAGE: &str = include_str!("../web/host.html");
+const BUZZER_PAGE: &str = include_str!("../web/buzzer.html");
+const OVERLAY_PAGE: &str = include_str!("../web/overlay.html");
+const HOST_KEY_LENGTH: usize = 6;
+const EVENT_STREAM_KEEPALIVE: Duration = Duration::from_secs(15);
+/// How many round trip measurements to keep for each buzzer client
+const LATENCY_SAMPLES: usize = 5;
+/// The most a phone's buzz is moved earlier to make up for its latency. It's measured with the
+/// phone's help, so it's kept well inside the time a late buzz can overturn the winner in.
+const MAX_LATENCY_COMPENSATION: Duration = Duration::from_millis(200);
+/// Round trips longer than this are dropped rather than counted, as a phone that was slow to
+/// answer one ping says little about how long its buzzes take
+const MAX_ROUND_TRIP: Duration = Duration::from_millis(1000);
+
+/// The latest snapshot as JSON, for the host and for everyone else, along with a version number
+/// so event streams can tell when it has changed
+struct Published {
+    version: u64,
+    host_json: String,
+    public_json: String,
+}
+
+/// Round trip times to a phone buzzer, measured by the server sending a ping and timing how long
+/// it takes for the phone to answer with a pong
+#[derive(Debug, Default)]
+struct ClientLatency {
+    ping_sent: Option<Instant>,
+    round_trips: VecDeque<Duration>,
+}
+
+impl ClientLatency {
+    /// Estimated time for a buzz to get from the phone to the server. Uses the median round trip
+    /// so one slow measurement doesn't throw it off, and never more than the maximum.
+    fn one_way(&self) -> Duration {
+        let mut round_trips: Vec<Duration> = self.round_trips.iter().copied().collect();
+        round_trips.sort();
+        round_trips
+            .get(round_trips.len() / 2)
+            .map(|rtt| *rtt / 2)
+            .unwrap_or_default()
+            .min(MAX_LATENCY_COMPENSATION)
+    }
+
+    fn record_round_trip(&mut self, round_trip: Duration) {
+        if round_trip > MAX_ROUND_TRIP {
+            return;
+        }
+        self.round_trips.push_back(round_trip);
+        if self.round_trips.len() > LATENCY_SAMPLES {
+            self.round_trips.pop_front();
+        }
+    }
+}
+
+struct Shared {
+    published: Mutex<Published>,
+    changed: Condvar,
+    host_key: String,
+    actions: Sender<(Action, Instant)>,
+    /// Wakes the main loop up, which may be waiting for events
+    wake: Box<dyn Fn() + Send + Sync>,
+    // keyed by the id each buzzer page makes up for itself
+    buzzer_clients: Mutex<HashMap<String, ClientLatency>>,
+}
+
+/// An optional HTTP server for controlling the game from a phone, and for teams to buzz in from
+/// theirs. Actions posted to it are sent down a channel to the main loop, where they are applied
+/// the same way as key presses.
+///
+/// The public state at `/public/state` (JSON) and `/public/events` (server-sent events) is also
+/// what stream overlays are built on, with `/overlay` as a ready-made browser source.
+pub struct RemoteServer {
+    shared: Arc<Shared>,
+}
+
+impl RemoteServer {
+    pub fn start(
+        address: &str,
+        actions: Sender<(Action, Instant)>,
+        wake: impl Fn() + Send + Sync + 'static,
+    ) -> Result<Self, String> {
+        let server = tiny_http::Server::http(address).map_err(|e| e.to_string())?;
+
+        let host_key: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(HOST_KEY_LENGTH)
+            .map(char::from)
+            .collect();
+
+        println!("Remote control at http://{}/?key={}", address, host_key);
+        println!("Team buzzers at http://{}/buzzer", address);
+        println!("Stream overlay at http://{}/overlay", address);
+
+        let shared = Arc::new(Shared {
+            published: Mutex::new(Published {
+                version: 0,
+                host_json: "{}".into(),
+                public_json: "{}".into(),
+            }),
+            changed: Condvar::new(),
+            host_key,
+            actions,
+            wake: Box::new(wake),
+            buzzer_clients: Mutex::new(HashMap::new()),
+        });
+
+        let thread_shared = shared.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                handle_request(request, &thread_shared);
+            }
+        });
+
+        Ok(RemoteServer { shared })
+    }
+
+    /// Makes a new snapshot available to clients. Cheap if nothing has changed.
+    pub fn publish(&self, snapshot: &GameSnapshot) {
+        let host_json = serde_json::to_string(snapshot).unwrap();
+
+        let mut published = self.shared.published.lock().unwrap();
+        if published.host_json != host_json {
+            published.host_json = host_json;
+            published.public_json = serde_json::to_string(&snapshot.public()).unwrap();
+            published.version += 1;
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+fn handle_request(request: Request, shared: &Arc<Shared>) {
+    let url = request.url().to_string();
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (url, String::new()),
+    };
+
+    let authorised = query_param(&query, "key") == Some(shared.host_key.as_str());
+    let client = query_param(&query, "client").map(|c| c.to_string());
+
+    let result = match (request.method(), path.as_str()) {
+        (Method::Get, "/") => request.respond(html_response(HOST_PAGE)),
+        (Method::Get, "/buzzer") => request.respond(html_response(BUZZER_PAGE)),
+        (Method::Get, "/overlay") => request.respond(html_response(OVERLAY_PAGE)),
+        (Method::Get, "/buzzer/ping") => {
+            if let Some(client) = client {
+                let mut buzzer_clients = shared.buzzer_clients.lock().unwrap();
+                buzzer_clients.entry(client).or_default().ping_sent = Some(Instant::now());
+            }
+            request.respond(Response::empty(204))
+        }
+        (Method::Post, "/buzzer/pong") => {
+            let mut buzzer_clients = shared.buzzer_clients.lock().unwrap();
+            if let Some(latency) = client.and_then(|c| buzzer_clients.get_mut(&c)) {
+                if let Some(ping_sent) = latency.ping_sent.take() {
+                    latency.record_round_trip(ping_sent.elapsed());
+                }
+            }
+            drop(buzzer_clients);
+            request.respond(Response::empty(204))
+        }
+        (Method::Post, "/buzzer/buzz") => {
+            let action = match query_param(&query, "team") {
+                Some("red") => Some(Action::BuzzRed),
+                Some("blue") => Some(Action::BuzzBlue),
+                _ => None,
+            };
+            match action {
+                Some(action) => {
+                    let one_way = client
+                        .and_then(|c| {
+                            shared
+                                .buzzer_clients
+                                .lock()
+                                .unwrap()
+                                .get(&c)
+                                .map(|l| l.one_way())
+                        })
+                        .unwrap_or_default();
+                    // the team pressed the button before the request got here
+                    let now = Instant::now();
+                    send_action(shared, action, now.checked_sub(one_way).unwrap_or(now));
+                    request.respond(Response::empty(204))
+                }
+                None => request.respond(Response::empty(400)),
+            }
+        }
+        // the public state is for buzzers and stream overlays, so it's open to anyone
+        (Method::Get, "/public/state") => {
+            let json = shared.published.lock().unwrap().public_json.clone();
+            request.respond(json_response(json))
+        }
+        (Method::Get, "/public/events") => {
+            let shared = shared.clone();
+            thread::spawn(move || stream_events(request, &shared, false));
+            Ok(())
+        }
+        (_, _) if !authorised => request.respond(Response::empty(403)),
+        (Method::Get, "/state") => {
+            let json = shared.published.lock().unwrap().host_json.clone();
+            request.respond(json_response(json))
+        }
+        (Method::Get, "/events") => {
+            let shared = shared.clone();
+            // event streams stay open, so give each one its own thread
+            thread::spawn(move || stream_events(request, &shared, true));
+            Ok(())
+        }
+        (Method::Post, action_path) if action_path.starts_with("/action/") => {
+            match parse_action(&action_path["/action/".len()..]) {
+                Some(action) => {
+                    send_action(shared, action, Instant::now());
+                    request.respond(Response::empty(204))
+                }
+                None => request.respond(Response::empty(404)),
+            }
+        }
+        _ => request.respond(Response::empty(404)),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error responding to remote request: {}", e);
+    }
+}
+
+fn send_action(shared: &Shared, action: Action, at: Instant) {
+    // the main loop only goes away when the game is quitting
+    if shared.actions.send((action, at)).is_ok() {
+        (shared.wake)();
+    }
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            _ => None,
+        })
+}
+
+fn parse_action(name: &str) -> Option<Action> {
+    // the name on its own, as it's written in controls.yaml, rather than parsed as YAML
+    let deserializer: StrDeserializer<ValueError> = name.into_deserializer();
+    match Action::deserialize(deserializer) {
+        // quitting is left to whoever is at the keyboard
+        Ok(Action::Quit) | Err(_) => None,
+        Ok(action) => Some(action),
+    }
+}
+
+/// Writes a server-sent event every time a new snapshot is published, until the client goes
+/// away
+fn stream_events(request: Request, shared: &Shared, host: bool) {
+    let mut writer = request.into_writer();
+    let header = "HTTP/1.1 200 OK\r\n\
+                  Content-Type: text/event-stream\r\n\
+                  Cache-Control: no-cache\r\n\
+                  Access-Control-Allow-Origin: *\r\n\r\n";
+    if writer.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+
+    let mut last_version = None;
+    loop {
+        let published = shared.published.lock().unwrap();
+        let (published, _) = shared
+            .changed
+            .wait_timeout_while(published, EVENT_STREAM_KEEPALIVE, |p| {
+                Some(p.version) == last_version
+            })
+            .unwrap();
+
+        let message = if Some(published.version) == last_version {
+            // nothing new, but make sure the connection is still alive
+            ": keepalive\n\n".to_string()
+        } else {
+            last_version = Some(published.version);
+            let json = if host {
+                &published.host_json
+            } else {
+                &published.public_json
+            };
+            format!("data: {}\n\n", json)
+        };
+        drop(published);
+
+        if writer.write_all(message.as_bytes()).is_err() || writer.flush().is_err() {
+            return;
+        }
+    }
+}
+
+fn html_response(body: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_header(Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap())
+}
+
+fn json_response(body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+        .with_header(Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap())
+}
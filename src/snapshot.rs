@@ -0,0 +1,109 @@
+use serde::Serialize;
+
+use crate::buzzers::Team;
+use crate::questions::QuestionType;
+
+/// Everything a display other than the main window needs to know about the game, built from the
+/// same state the render loop draws from
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct GameSnapshot {
+    /// One of "start", "title", "question" or "end"
+    pub screen: &'static str,
+    pub set_title: Option<String>,
+    pub red_score: i32,
+    pub blue_score: i32,
+    /// While a question is up, one of "count_in", "playing", "passed_over" or "answer_shown"
+    pub phase: Option<&'static str>,
+    pub team_in_play: Option<Team>,
+    pub question_type: Option<QuestionType>,
+    /// Every clue in the question, including the ones that haven't been revealed yet
+    pub clues: Vec<String>,
+    /// Which clues are pictures, whose text is their caption
+    pub pictures: Vec<bool>,
+    /// Whether picture captions are on screen, which they normally only are with the answer
+    pub captions_shown: bool,
+    pub clues_shown: usize,
+    pub fourth_clue_hidden: bool,
+    pub connection: Option<String>,
+    pub answer_shown: bool,
+    pub passed_over: bool,
+    pub clock_stopped: bool,
+    pub paused: bool,
+    pub points_available: Option<i32>,
+    pub seconds_remaining: Option<u64>,
+    pub buzz: Option<BuzzSnapshot>,
+    pub notes: Vec<String>,
+}
+
+impl GameSnapshot {
+    /// The snapshot with everything the audience shouldn't see yet removed
+    pub fn public(&self) -> GameSnapshot {
+        let mut public = self.clone();
+
+        let clues_visible = if self.fourth_clue_hidden {
+            self.clues_shown.min(3)
+        } else {
+            self.clues_shown
+        };
+        public.clues.truncate(clues_visible);
+        public.pictures.truncate(clues_visible);
+        if !self.captions_shown {
+            for (clue, &picture) in public.clues.iter_mut().zip(&public.pictures) {
+                if picture {
+                    clue.clear();
+                }
+            }
+        }
+
+        if !self.answer_shown {
+            public.connection = None;
+        }
+        public.notes.clear();
+
+        public
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuzzSnapshot {
+    pub team: Team,
+    pub seconds: f64,
+    /// The team that looked to have buzzed first, until this buzz arrived late and took its place
+    pub overturned: Option<Team>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question() -> GameSnapshot {
+        GameSnapshot {
+            screen: "question",
+            clues: vec!["Taro".into(), "A cassava root".into(), "Turnip".into()],
+            pictures: vec![false, true, false],
+            clues_shown: 2,
+            connection: Some("Root vegetables".into()),
+            notes: vec!["Not potatoes".into()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn public_snapshot_hides_what_is_not_on_screen() {
+        let public = question().public();
+        assert_eq!(public.clues, ["Taro", ""]);
+        assert_eq!(public.pictures, [false, true]);
+        assert_eq!(public.connection, None);
+        assert!(public.notes.is_empty());
+    }
+
+    #[test]
+    fn public_snapshot_has_captions_once_they_are_shown() {
+        let mut snapshot = question();
+        snapshot.answer_shown = true;
+        snapshot.captions_shown = true;
+        let public = snapshot.public();
+        assert_eq!(public.clues, ["Taro", "A cassava root"]);
+        assert_eq!(public.connection.as_deref(), Some("Root vegetables"));
+    }
+}
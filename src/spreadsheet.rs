@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use csv::{ReaderBuilder, StringRecord};
+
+use crate::questions::{
+    clue_problem, parse_tags, Clue, Picture, Question, QuestionSet, QuestionType,
+};
+
+/// Columns every spreadsheet needs
+const REQUIRED_COLUMNS: [&str; 6] = ["type", "connection", "clue1", "clue2", "clue3", "clue4"];
+
+/// Loads a question set from a spreadsheet exported as CSV, or TSV if the file ends in .tsv. The
+/// first row names the columns:
+///
+/// - `type` (connection or sequence), `connection` and `clue1` to `clue4`
+/// - optionally `picture1` to `picture4`, which make that clue a picture with the clue as its
+///   caption
+/// - optionally `notes`, with a note on each line of the cell, and `author`, `difficulty` and
+///   `tags` (separated by commas)
+///
+/// Other columns are ignored, as are empty rows. The set is titled after the file name.
+pub fn load_spreadsheet<P: AsRef<Path>>(path: P) -> Result<QuestionSet, Vec<String>> {
+    let path = path.as_ref();
+    let is_tsv = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("tsv"));
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(if is_tsv { b'\t' } else { b',' })
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| vec![format!("{}: {}", path.display(), e)])?;
+
+    let columns: HashMap<String, usize> = reader
+        .headers()
+        .map_err(|e| vec![format!("{}: {}", path.display(), e)])?
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_lowercase(), i))
+        .collect();
+    let missing: Vec<String> = REQUIRED_COLUMNS
+        .iter()
+        .filter(|&&name| !columns.contains_key(name))
+        .map(|name| format!("{}: no {} column", path.display(), name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    let mut questions = vec![];
+    let mut errors = vec![];
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+
+        let line = record.position().map_or(0, |p| p.line());
+        match read_row(&columns, &record) {
+            Ok(question) => questions.push(question),
+            Err(row_errors) => errors.extend(
+                row_errors
+                    .into_iter()
+                    .map(|e| format!("{} line {}: {}", path.display(), line, e)),
+            ),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let title = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().replace('_', " "));
+    Ok(QuestionSet { title, questions })
+}
+
+fn read_row(
+    columns: &HashMap<String, usize>,
+    record: &StringRecord,
+) -> Result<Question, Vec<String>> {
+    let cell = |name: &str| {
+        columns
+            .get(name)
+            .and_then(|&i| record.get(i))
+            .map_or("", str::trim)
+    };
+    let optional_cell = |name: &str| Some(cell(name)).filter(|value| !value.is_empty());
+    // line breaks typed into a cell become markup, so they survive being written out as lines
+    let text_cell = |name: &str| cell(name).replace("\r\n", "\n").replace('\n', "\\n");
+
+    let mut errors = vec![];
+
+    let question_type = match cell("type").to_lowercase().as_str() {
+        "connection" => QuestionType::Connection,
+        "sequence" => QuestionType::Sequence,
+        other => {
+            errors.push(format!(
+                "Unknown type \"{}\", expected connection or sequence",
+                other
+            ));
+            QuestionType::Connection
+        }
+    };
+
+    let connection = text_cell("connection");
+    if connection.is_empty() {
+        errors.push("No connection".into());
+    }
+    let mut question = Question::new(question_type, connection);
+
+    for i in 1..=4 {
+        let text = text_cell(&format!("clue{}", i));
+        match optional_cell(&format!("picture{}", i)) {
+            Some(picture_path) => {
+                if text.is_empty() {
+                    errors.push(format!("Picture clue {} has no caption", i));
+                }
+                match Picture::load(picture_path) {
+                    Ok(picture) => question.clues.push(Clue::PictureClue(picture, text)),
+                    Err(e) => errors.push(format!("Could not load image {}: {}", picture_path, e)),
+                }
+            }
+            None if text.is_empty() => errors.push(format!("No clue {}", i)),
+            None => question.clues.push(Clue::TextClue(text)),
+        }
+    }
+
+    // the question file has to be able to tell each clue apart from the lines around it
+    // (empty captions have already been reported)
+    for (i, clue) in question.clues.iter().enumerate() {
+        if clue.text().is_empty() {
+            continue;
+        }
+        if let Some(problem) = clue_problem(clue) {
+            errors.push(format!("Clue {} {}", i + 1, problem));
+        }
+    }
+
+    question.notes = cell("notes")
+        .lines()
+        .map(str::trim)
+        .filter(|note| !note.is_empty())
+        .map(String::from)
+        .collect();
+    question.metadata.author = optional_cell("author").map(String::from);
+    question.metadata.difficulty = optional_cell("difficulty").map(String::from);
+    question.metadata.tags = parse_tags(cell("tags"));
+
+    if errors.is_empty() {
+        Ok(question)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::questions::{load_questions, save_questions};
+
+    #[test]
+    fn line_breaks_in_cells_become_markup_and_survive_saving() {
+        let directory = std::env::temp_dir();
+        let sheet_path = directory.join("spreadsheet_test.csv");
+        std::fs::write(
+            &sheet_path,
+            "Type,Connection,Clue1,Clue2,Clue3,Clue4,Notes,Tags,Ignored\r\n\
+             connection,\"Two\r\nlines\",a,b,\"c\nd\",e,\"first\n second \",\"Music, FILM\",x\r\n\
+             ,,,,,,,,\r\n",
+        )
+        .unwrap();
+
+        let question_set = load_spreadsheet(&sheet_path).unwrap();
+        assert_eq!(question_set.title, "spreadsheet test");
+        let question = &question_set.questions[0];
+        assert_eq!(question.connection, r"Two\nlines");
+        assert_eq!(question.clues[2].text(), r"c\nd");
+        assert_eq!(question.notes, ["first", "second"]);
+        assert_eq!(question.metadata.tags, ["music", "film"]);
+
+        let saved_path = directory.join("spreadsheet-test-saved.txt");
+        save_questions(&saved_path, &question_set).unwrap();
+        let reloaded = load_questions(&saved_path).unwrap();
+        assert_eq!(
+            format!("{:?}", reloaded.questions),
+            format!("{:?}", question_set.questions)
+        );
+
+        std::fs::remove_file(&sheet_path).unwrap();
+        std::fs::remove_file(&saved_path).unwrap();
+    }
+
+    #[test]
+    fn missing_columns_and_bad_rows_are_reported() {
+        let path = std::env::temp_dir().join("spreadsheet-test-errors.tsv");
+        std::fs::write(&path, "type\tconnection\tclue1\n").unwrap();
+        assert_eq!(load_spreadsheet(&path).err().unwrap().len(), 3);
+
+        std::fs::write(
+            &path,
+            "type\tconnection\tclue1\tclue2\tclue3\tclue4\n\
+             puzzle\t\ta\tb\tc\tnote: d\n",
+        )
+        .unwrap();
+        let errors = load_spreadsheet(&path).err().unwrap();
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().all(|e| e.contains("line 2")));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
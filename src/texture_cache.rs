@@ -0,0 +1,133 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use sdl2::pixels::Color;
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::surface::Surface;
+
+use crate::fonts::{FontChain, ScalableFont};
+use crate::BackgroundColour;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TextKey {
+    text: String,
+    font: String,
+    width: u32,
+    height: u32,
+    colour: Color,
+}
+
+/// Textures for rendered text and pictures, kept between frames so they're only rendered and
+/// uploaded once. Nothing is ever evicted, so it should be cleared whenever the layout or the
+/// question changes.
+pub struct TextureCache<'r, C> {
+    texture_creator: &'r TextureCreator<C>,
+    text: HashMap<TextKey, Texture<'r>>,
+    // single lines in one font, so it has to be cleared when the font changes too
+    lines: HashMap<(String, Color), Texture<'r>>,
+    // keyed by clue number, which is why the cache has to be cleared between questions
+    pictures: HashMap<usize, Texture<'r>>,
+    backgrounds: HashMap<BackgroundColour, Texture<'r>>,
+}
+
+impl<'r, C> TextureCache<'r, C> {
+    pub fn new(texture_creator: &'r TextureCreator<C>) -> Self {
+        TextureCache {
+            texture_creator,
+            text: HashMap::new(),
+            lines: HashMap::new(),
+            pictures: HashMap::new(),
+            backgrounds: HashMap::new(),
+        }
+    }
+
+    /// Text rendered as by `render_text`
+    pub fn text(
+        &mut self,
+        text: &str,
+        fonts: &ScalableFont,
+        width: u32,
+        height: u32,
+        padding: u32,
+        colour: Color,
+    ) -> Result<&Texture<'r>, String> {
+        let key = TextKey {
+            text: text.to_string(),
+            font: fonts.main_path().to_string(),
+            width,
+            height,
+            colour,
+        };
+
+        if !self.text.contains_key(&key) {
+            let surface = crate::render_text(text, fonts, width, height, padding, colour)?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(surface)
+                .map_err(|e| e.to_string())?;
+            self.text.insert(key.clone(), texture);
+        }
+
+        Ok(&self.text[&key])
+    }
+
+    /// A line of text as rendered by `FontChain::render`
+    pub fn line(
+        &mut self,
+        text: &str,
+        font: &FontChain,
+        colour: Color,
+    ) -> Result<&Texture<'r>, String> {
+        let key = (text.to_string(), colour);
+
+        if !self.lines.contains_key(&key) {
+            let surface = font.render(text, colour)?;
+            let texture = self
+                .texture_creator
+                .create_texture_from_surface(surface)
+                .map_err(|e| e.to_string())?;
+            self.lines.insert(key.clone(), texture);
+        }
+
+        Ok(&self.lines[&key])
+    }
+
+    /// The picture for a clue
+    pub fn picture(&mut self, clue_index: usize, image: &Surface) -> Result<&Texture<'r>, String> {
+        match self.pictures.entry(clue_index) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let texture = self
+                    .texture_creator
+                    .create_texture_from_surface(image)
+                    .map_err(|e| e.to_string())?;
+                Ok(entry.insert(texture))
+            }
+        }
+    }
+
+    /// The theme's image for a background colour
+    pub fn background(
+        &mut self,
+        background: BackgroundColour,
+        image: &Surface,
+    ) -> Result<&Texture<'r>, String> {
+        match self.backgrounds.entry(background) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let texture = self
+                    .texture_creator
+                    .create_texture_from_surface(image)
+                    .map_err(|e| e.to_string())?;
+                Ok(entry.insert(texture))
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.lines.clear();
+        self.pictures.clear();
+        self.backgrounds.clear();
+    }
+}
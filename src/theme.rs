@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+
+use sdl2::image::LoadSurface;
+use sdl2::pixels::Color;
+use sdl2::surface::Surface;
+use sdl2::ttf::Sdl2TtfContext;
+use serde::Deserialize;
+
+use crate::animation::AnimationSettings;
+use crate::fonts::ScalableFont;
+use crate::BackgroundColour;
+
+/// Everything on the game screen that has its own colour
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColourRole {
+    BackgroundGrey,
+    BackgroundRed,
+    BackgroundBlue,
+    TileBackground,
+    TileText,
+    AnswerBackground,
+    AnswerText,
+    /// The strip over the bottom of a picture clue for its caption
+    CaptionBackground,
+    CaptionText,
+    /// Text filling the screen: the start, title and end pages
+    BannerText,
+    ProgressBarBackground,
+    ProgressBarForeground,
+    /// Points on the progress bar, the count-in, the countdown and the pause banner
+    ProgressBarText,
+    RedScoreTile,
+    BlueScoreTile,
+    ScoreTileText,
+    HelpOverlay,
+    HelpText,
+}
+
+impl ColourRole {
+    pub const ALL: [ColourRole; 18] = [
+        ColourRole::BackgroundGrey,
+        ColourRole::BackgroundRed,
+        ColourRole::BackgroundBlue,
+        ColourRole::TileBackground,
+        ColourRole::TileText,
+        ColourRole::AnswerBackground,
+        ColourRole::AnswerText,
+        ColourRole::CaptionBackground,
+        ColourRole::CaptionText,
+        ColourRole::BannerText,
+        ColourRole::ProgressBarBackground,
+        ColourRole::ProgressBarForeground,
+        ColourRole::ProgressBarText,
+        ColourRole::RedScoreTile,
+        ColourRole::BlueScoreTile,
+        ColourRole::ScoreTileText,
+        ColourRole::HelpOverlay,
+        ColourRole::HelpText,
+    ];
+
+    fn default_colour(self) -> Color {
+        match self {
+            ColourRole::BackgroundGrey => Color::RGB(0x66, 0x66, 0x66),
+            ColourRole::BackgroundRed => Color::RGB(0x66, 0x33, 0x33),
+            ColourRole::BackgroundBlue => Color::RGB(0x33, 0x33, 0xcc),
+            ColourRole::TileBackground | ColourRole::AnswerBackground => {
+                Color::RGB(0x99, 0x99, 0x99)
+            }
+            ColourRole::TileText | ColourRole::AnswerText | ColourRole::BannerText => {
+                Color::RGB(0x33, 0x33, 0x33)
+            }
+            ColourRole::CaptionBackground => Color::RGBA(0x33, 0x33, 0x33, 0xcc),
+            ColourRole::CaptionText => Color::RGB(0xff, 0xff, 0xff),
+            ColourRole::ProgressBarBackground => Color::RGB(0x33, 0x33, 0x33),
+            ColourRole::ProgressBarForeground => Color::RGB(0x99, 0x99, 0x99),
+            ColourRole::ProgressBarText => Color::RGB(0xff, 0xff, 0xff),
+            ColourRole::RedScoreTile => Color::RGB(0x99, 0x66, 0x66),
+            ColourRole::BlueScoreTile => Color::RGB(0x66, 0x66, 0xff),
+            ColourRole::ScoreTileText => Color::RGB(0xff, 0xff, 0xff),
+            ColourRole::HelpOverlay => Color::RGBA(0x00, 0x00, 0x00, 0xcc),
+            ColourRole::HelpText => Color::RGB(0xff, 0xff, 0xff),
+        }
+    }
+}
+
+/// The kinds of text that can each have their own font
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextElement {
+    Tiles,
+    Answer,
+    /// Score tiles, the progress bar and the clock
+    Score,
+    /// The start, title and end pages, and the buzz, pause and help text
+    Banner,
+}
+
+impl TextElement {
+    pub const ALL: [TextElement; 4] = [
+        TextElement::Tiles,
+        TextElement::Answer,
+        TextElement::Score,
+        TextElement::Banner,
+    ];
+}
+
+pub struct Theme {
+    colours: HashMap<ColourRole, Color>,
+    /// The main font for each element that doesn't use the one from the font list
+    fonts: HashMap<TextElement, String>,
+    backgrounds: HashMap<BackgroundColour, Surface<'static>>,
+    animations: AnimationSettings,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            colours: ColourRole::ALL
+                .iter()
+                .map(|&role| (role, role.default_colour()))
+                .collect(),
+            fonts: HashMap::new(),
+            backgrounds: HashMap::new(),
+            animations: AnimationSettings::default(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn colour(&self, role: ColourRole) -> Color {
+        self.colours[&role]
+    }
+
+    /// The image drawn over a background colour, if there is one
+    pub fn background_image(&self, background: BackgroundColour) -> Option<&Surface<'static>> {
+        self.backgrounds.get(&background)
+    }
+
+    pub fn animations(&self) -> &AnimationSettings {
+        &self.animations
+    }
+
+    /// Loads the fonts for each element at a size. Elements without a font of their own use the
+    /// main font from the font list, and every element uses its fallbacks.
+    pub fn load_fonts<'ttf>(
+        &self,
+        ttf_context: &'ttf Sdl2TtfContext,
+        font_paths: &[String],
+        size: u16,
+    ) -> Result<ElementFonts<'ttf>, String> {
+        // elements with the same font share it, rather than loading it again at every size
+        let mut loaded: HashMap<&str, Rc<ScalableFont>> = HashMap::new();
+        let mut fonts = HashMap::new();
+
+        for &element in TextElement::ALL.iter() {
+            let main_path = self.fonts.get(&element).unwrap_or(&font_paths[0]);
+            let font = match loaded.get(main_path.as_str()) {
+                Some(font) => font.clone(),
+                None => {
+                    let mut paths = vec![main_path.clone()];
+                    paths.extend_from_slice(&font_paths[1..]);
+                    let font = Rc::new(
+                        ScalableFont::load(ttf_context, &paths, size)
+                            .map_err(|e| format!("Could not load font {}: {}", main_path, e))?,
+                    );
+                    loaded.insert(main_path, font.clone());
+                    font
+                }
+            };
+            fonts.insert(element, font);
+        }
+
+        Ok(ElementFonts { fonts })
+    }
+}
+
+/// The fonts for each kind of text on the game screen
+pub struct ElementFonts<'ttf> {
+    fonts: HashMap<TextElement, Rc<ScalableFont<'ttf>>>,
+}
+
+impl<'ttf> ElementFonts<'ttf> {
+    pub fn get(&self, element: TextElement) -> &ScalableFont<'ttf> {
+        &self.fonts[&element]
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ThemeFile {
+    colours: HashMap<ColourRole, String>,
+    fonts: HashMap<TextElement, Vec<String>>,
+    backgrounds: HashMap<BackgroundColour, String>,
+    animations: AnimationSettings,
+}
+
+/// Loads a theme file. Colours it doesn't mention keep their defaults, and elements without a
+/// font use the main font. If the file doesn't exist and it's `optional`, the default theme is
+/// used.
+pub fn load_theme<P: AsRef<Path>>(path: P, optional: bool) -> Result<Theme, Vec<String>> {
+    let path = path.as_ref();
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && optional => {
+            return Ok(Theme::default())
+        }
+        Err(e) => return Err(vec![format!("{}: {}", path.display(), e)]),
+    };
+
+    let theme_file: ThemeFile = serde_yaml::from_reader(file).map_err(|e| vec![e.to_string()])?;
+
+    let mut errors = vec![];
+    let mut theme = Theme {
+        animations: theme_file.animations,
+        ..Theme::default()
+    };
+
+    for (role, value) in theme_file.colours {
+        match parse_colour(&value) {
+            Some(colour) => {
+                theme.colours.insert(role, colour);
+            }
+            None => errors.push(format!(
+                "Invalid colour {} for {:?}, expected #rrggbb or #rrggbbaa",
+                value, role
+            )),
+        }
+    }
+
+    // like fallback fonts, the first font that exists is used, so one theme can list fonts for
+    // several platforms
+    for (element, paths) in theme_file.fonts {
+        match paths.iter().find(|path| Path::new(path).exists()) {
+            Some(path) => {
+                theme.fonts.insert(element, path.clone());
+            }
+            None => eprintln!(
+                "None of the fonts for {:?} exist, using the main font instead",
+                element
+            ),
+        }
+    }
+
+    for (background, path) in theme_file.backgrounds {
+        match Surface::from_file(&path) {
+            Ok(image) => {
+                theme.backgrounds.insert(background, image);
+            }
+            Err(e) => errors.push(format!("Could not load background {}: {}", path, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(theme)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses a colour written as #rrggbb, or #rrggbbaa with transparency
+fn parse_colour(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    match hex.len() {
+        6 => Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?)),
+        8 => Some(Color::RGBA(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        )),
+        _ => None,
+    }
+}
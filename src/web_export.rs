@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::controls::{Action, Controls};
+use crate::markup;
+use crate::questions::{Clue, QuestionSet, QuestionType};
+use crate::{COUNTDOWN_TIME, COUNT_IN_TIME, TIME_PER_QUESTION};
+
+const GAME_PAGE: &str = include_str!("../web/game.html");
+/// Where the game's data goes in the page
+const GAME_DATA_PLACEHOLDER: &str = "/* GAME_DATA */ null";
+
+/// The host controls the web version has, which are the SDL version's apart from the ones for
+/// sound and quitting
+const WEB_ACTIONS: [Action; 16] = [
+    Action::Next,
+    Action::Stop,
+    Action::Correct,
+    Action::Incorrect,
+    Action::Undo,
+    Action::Pause,
+    Action::RedScoreUp,
+    Action::RedScoreDown,
+    Action::BlueScoreUp,
+    Action::BlueScoreDown,
+    Action::BuzzRed,
+    Action::BuzzBlue,
+    Action::ResetBuzzers,
+    Action::ToggleHelp,
+    Action::ToggleCaptions,
+    Action::ToggleFullscreen,
+];
+
+#[derive(Serialize)]
+struct GameData {
+    red_goes_first: bool,
+    seconds_per_question: u64,
+    count_in_seconds: u64,
+    countdown_seconds: u64,
+    /// SDL names of the keys for each action
+    keys: HashMap<Action, Vec<String>>,
+    help: Vec<String>,
+    sets: Vec<SetData>,
+}
+
+#[derive(Serialize)]
+struct SetData {
+    title: String,
+    questions: Vec<QuestionData>,
+}
+
+/// A question with its text as HTML, so markup looks the same as on the game screen
+#[derive(Serialize)]
+struct QuestionData {
+    question_type: QuestionType,
+    connection: String,
+    clues: Vec<ClueData>,
+}
+
+#[derive(Serialize)]
+struct ClueData {
+    text: String,
+    /// The picture as a data URL, for picture clues
+    picture: Option<String>,
+}
+
+/// Writes the questions out as a single HTML page that plays the game in a browser, with the
+/// pictures embedded in it and the same key bindings as the game
+pub fn write_web_game<P: AsRef<Path>>(
+    path: P,
+    question_sets: &[QuestionSet],
+    controls: &Controls,
+    red_goes_first: bool,
+) -> Result<(), Vec<String>> {
+    let mut errors = vec![];
+
+    let mut sets = vec![];
+    for question_set in question_sets {
+        let mut questions = vec![];
+        for question in &question_set.questions {
+            let clues = question
+                .clues
+                .iter()
+                .map(|clue| match clue {
+                    Clue::TextClue(text) => ClueData {
+                        text: markup::html(text),
+                        picture: None,
+                    },
+                    Clue::PictureClue(picture, caption) => ClueData {
+                        text: markup::html(caption),
+                        picture: match data_url(&picture.path) {
+                            Ok(url) => Some(url),
+                            Err(e) => {
+                                errors
+                                    .push(format!("Could not embed image {}: {}", picture.path, e));
+                                None
+                            }
+                        },
+                    },
+                })
+                .collect();
+            questions.push(QuestionData {
+                question_type: question.question_type,
+                connection: markup::html(&question.connection),
+                clues,
+            });
+        }
+        sets.push(SetData {
+            title: markup::escape_html(&question_set.title),
+            questions,
+        });
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let data = GameData {
+        red_goes_first,
+        seconds_per_question: TIME_PER_QUESTION.as_secs(),
+        count_in_seconds: COUNT_IN_TIME.as_secs(),
+        countdown_seconds: COUNTDOWN_TIME.as_secs(),
+        keys: WEB_ACTIONS
+            .iter()
+            .map(|&action| (action, controls.key_names(action)))
+            .collect(),
+        help: WEB_ACTIONS
+            .iter()
+            .filter_map(|&action| {
+                let key_names = controls.key_names(action);
+                if key_names.is_empty() {
+                    None
+                } else {
+                    Some(format!(
+                        "{}: {}",
+                        key_names.join(", "),
+                        action.description()
+                    ))
+                }
+            })
+            .collect(),
+        sets,
+    };
+
+    // the data sits in a script element, which a "</script>" in a clue would end early
+    let json = serde_json::to_string(&data)
+        .map_err(|e| vec![e.to_string()])?
+        .replace("</", "<\\/");
+    let page = GAME_PAGE.replace(GAME_DATA_PLACEHOLDER, &json);
+
+    fs::write(path, page).map_err(|e| vec![e.to_string()])
+}
+
+/// Reads an image file into a data URL
+fn data_url(path: &str) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    let mime_type = match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "tif" | "tiff" => "image/tiff",
+        _ => "image/png",
+    };
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type,
+        base64::encode(bytes)
+    ))
+}